@@ -8,14 +8,108 @@ pub struct Cli {
     #[arg(short, long, default_value_t = Level::WARN)]
     ///set the minimum log level
     pub log_level: Level,
-    ///override the default config path (~/.config/embargo_bar/config.ron)
+    ///override the default config path (`~/.config/embargo_bar/config.toml`)
     #[arg(short = 'c', long)]
     pub override_config: Option<PathBuf>,
+    ///name of the instance to run or send messages to.  Instances with different names can run
+    ///side by side
+    #[arg(short, long)]
+    pub name: Option<String>,
     #[command(subcommand)]
     pub command: Option<Command>,
 }
 #[derive(Subcommand, Debug, Clone)]
 pub enum Command {
-    Run,
+    Run {
+        ///show every bar in a normal window instead of docking it to the screen edges
+        #[arg(long)]
+        windowed: bool,
+        ///ask an instance with the same name that is already running to exit, instead of refusing
+        ///to start
+        #[arg(long)]
+        replace: bool,
+    },
     PrintConfig,
+    ///render a bar with live data into a png without connecting to wayland
+    Screenshot {
+        #[arg(short, long)]
+        out: PathBuf,
+        ///size of the image as `WIDTHxHEIGHT`
+        #[arg(short, long, default_value = "1920x40", value_parser = parse_size)]
+        size: (u32, u32),
+        ///index of the `[[bar]]` to render
+        #[arg(short, long, default_value_t = 0)]
+        bar: usize,
+    },
+    ///send a message to the running instance over its control socket
+    Msg {
+        #[command(subcommand)]
+        message: Message,
+    },
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum Message {
+    ///set a property.  The value is parsed as JSON and taken as a string if that fails
+    Set {
+        name: String,
+        value: String,
+        ///set the property of this global instead
+        #[arg(short, long)]
+        global: Option<String>,
+        ///index of the `[[bar]]` to set it on.  Every bar with the property by default
+        #[arg(short, long)]
+        bar: Option<usize>,
+    },
+    ///print the current value of a property as JSON
+    Get {
+        name: String,
+        #[arg(short, long)]
+        global: Option<String>,
+        #[arg(short, long, default_value_t = 0)]
+        bar: usize,
+    },
+    ///run a script now, or all of them
+    Refresh { script: Option<String> },
+    ///read the config again and replace every bar
+    Reload,
+    ///show a bar, or every bar, again
+    Show {
+        ///only on the output with this name, e.g. `DP-1`
+        #[arg(short, long)]
+        output: Option<String>,
+        #[arg(short, long)]
+        bar: Option<usize>,
+    },
+    Hide {
+        #[arg(short, long)]
+        output: Option<String>,
+        #[arg(short, long)]
+        bar: Option<usize>,
+    },
+    Toggle {
+        #[arg(short, long)]
+        output: Option<String>,
+        #[arg(short, long)]
+        bar: Option<usize>,
+    },
+    ///hide a bar, or every bar, until the pointer touches its edge of the screen.  Bars without
+    ///`auto_hide` in the config use the default timings
+    AutoHide {
+        ///turn it off again
+        #[arg(long)]
+        off: bool,
+        #[arg(short, long)]
+        bar: Option<usize>,
+    },
+    ///pop up the on-screen display with a value between 0 and 1
+    Osd { icon: String, value: f32 },
+}
+
+fn parse_size(size: &str) -> Result<(u32, u32), String> {
+    let (width, height) = size
+        .split_once('x')
+        .ok_or_else(|| format!("'{size}' is not of the form WIDTHxHEIGHT"))?;
+    let parse = |n: &str| n.parse::<u32>().map_err(|e| format!("'{n}': {e}"));
+    Ok((parse(width)?, parse(height)?))
 }
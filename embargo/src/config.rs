@@ -3,13 +3,13 @@ use std::{
     path::{Path, PathBuf},
 };
 
-use layer_platform::Anchor;
+use layer_platform::{Anchor, InputRegion, OutputFilter, Rect};
 use tracing::Level;
-mod timings;
+pub mod timings;
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct Script {
-    update: timings::Refresh,
-    script: String,
+    pub update: timings::Refresh,
+    pub script: String,
 }
 impl Script {
     fn example() -> Self {
@@ -21,12 +21,55 @@ impl Script {
 }
 #[derive(Debug)]
 pub struct Config {
-    //    slint_file: PathBuf,
+    pub path: PathBuf,
+    pub bars: Vec<BarConfig>,
+    pub osd: OsdConfig,
+    pub scripts: HashMap<String, Script>,
+    pub hardware: HardwareConfig,
+}
+/// What the hardware monitor needs from the config
+#[derive(Debug, Clone)]
+pub struct HardwareConfig {
+    pub network_interface: NetworkInterface,
+    pub history: HistoryConfig,
+    pub format: FormatConfig,
+    pub sensors: SensorsConfig,
+    pub battery: BatteryConfig,
+    pub disks: DisksConfig,
+    pub processes: ProcessesConfig,
+    pub sysfs_root: PathBuf,
+    pub procfs_root: PathBuf,
+}
+#[derive(Debug)]
+pub struct BarConfig {
     pub anchor: Anchor,
-    pub config_path: PathBuf,
     pub slint_entrypoint: PathBuf,
     pub layer_name: String,
-    pub scripts: HashMap<String, Script>,
+    pub outputs: OutputFilter,
+    pub width: u32,
+    pub height: u32,
+    pub auto_hide: Option<AutoHideConfig>,
+    pub input_region: InputRegion,
+}
+impl BarConfig {
+    fn new(bar_file: BarFile, config_dir: &Path) -> Self {
+        Self {
+            slint_entrypoint: bar_file
+                .slint_entrypoint
+                .unwrap_or_else(|| config_dir.join("slint").join("main.slint")),
+            layer_name: bar_file.layer_name,
+            anchor: bar_file.anchor.into(),
+            outputs: OutputFilter {
+                include: bar_file.outputs,
+                exclude: bar_file.exclude_outputs,
+                primary_only: bar_file.primary_only,
+            },
+            width: bar_file.width,
+            height: bar_file.height,
+            auto_hide: bar_file.auto_hide,
+            input_region: bar_file.input_region.into(),
+        }
+    }
 }
 impl Config {
     pub fn parse(override_path: Option<&Path>) -> anyhow::Result<Self> {
@@ -54,14 +97,31 @@ impl Config {
                 ConfigFile::default()
             }
         };
+        // `[[bar]]` entries replace the bar described at the top level
+        let bar_files = if config_file.bars.is_empty() {
+            vec![config_file.bar]
+        } else {
+            config_file.bars
+        };
         Ok(Self {
-            slint_entrypoint: config_file
-                .slint_entrypoint
-                .unwrap_or_else(|| config_dir.join("slint").join("main.slint")),
-            layer_name: config_file.layer_name,
+            bars: bar_files
+                .into_iter()
+                .map(|bar_file| BarConfig::new(bar_file, &config_dir))
+                .collect(),
             scripts: config_file.scripts,
-            anchor: config_file.anchor.into(),
-            config_path,
+            osd: config_file.osd,
+            hardware: HardwareConfig {
+                network_interface: config_file.network_interface,
+                history: config_file.history,
+                format: config_file.format,
+                sensors: config_file.sensors,
+                battery: config_file.battery,
+                disks: config_file.disks,
+                processes: config_file.processes,
+                sysfs_root: config_file.sysfs_root,
+                procfs_root: config_file.procfs_root,
+            },
+            path: config_path,
         })
     }
     fn default_config_dir() -> anyhow::Result<PathBuf> {
@@ -77,12 +137,232 @@ impl Config {
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 #[serde(default)]
 struct ConfigFile {
+    #[serde(flatten)]
+    bar: BarFile,
+    #[serde(rename = "bar", skip_serializing_if = "Vec::is_empty")]
+    bars: Vec<BarFile>,
+    scripts: HashMap<String, Script>,
+    osd: OsdConfig,
+    /// the interface the network stats are for.  `auto` follows the default route.
+    network_interface: NetworkInterface,
+    history: HistoryConfig,
+    format: FormatConfig,
+    sensors: SensorsConfig,
+    battery: BatteryConfig,
+    disks: DisksConfig,
+    processes: ProcessesConfig,
+    /// where sysfs is mounted.  Only worth changing to test against a copy.
+    sysfs_root: PathBuf,
+    /// where procfs is mounted, for the same reason
+    procfs_root: PathBuf,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+struct BarFile {
     anchor: SimpleAnchor,
     layer_name: String,
-    scripts: HashMap<String, Script>,
+    /// glob patterns of output names or descriptions to show the bar on.  Empty means all outputs
+    outputs: Vec<String>,
+    /// glob patterns of output names or descriptions to never show the bar on
+    exclude_outputs: Vec<String>,
+    /// only show the bar on the first output that matches
+    primary_only: bool,
+    /// width used until the size of an output is known
+    width: u32,
+    height: u32,
+    /// hide the bar until the pointer touches the screen edge
+    auto_hide: Option<AutoHideConfig>,
+    /// the part of the bar that takes pointer input.  Clicks elsewhere go to the windows below.
+    input_region: SimpleInputRegion,
     slint_entrypoint: Option<PathBuf>,
 }
 
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct AutoHideConfig {
+    /// how long to wait after the pointer left before hiding
+    pub delay_ms: u64,
+    /// thickness in pixels of the strip that reveals the bar
+    pub trigger_size: u32,
+    /// time the slint content gets to animate out, driven by its `revealed` property
+    pub animation_ms: u64,
+}
+impl Default for AutoHideConfig {
+    fn default() -> Self {
+        Self {
+            delay_ms: 500,
+            trigger_size: 2,
+            animation_ms: 0,
+        }
+    }
+}
+
+/// The popup shown by the `Osd.show(icon, value)` callback of a bar
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct OsdConfig {
+    pub width: u32,
+    pub height: u32,
+    /// how long the popup stays after the last time it was shown
+    pub timeout_ms: u64,
+    /// a component with `icon-name` and `value` properties.  A built-in one is used if unset.
+    pub slint_entrypoint: Option<PathBuf>,
+}
+impl Default for OsdConfig {
+    fn default() -> Self {
+        Self {
+            width: 300,
+            height: 64,
+            timeout_ms: 1500,
+            slint_entrypoint: None,
+        }
+    }
+}
+
+/// The metrics kept for graphs, published as `<metric>_history` and `<metric>_history_path`
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct HistoryConfig {
+    /// how many samples are kept
+    pub length: usize,
+    /// time between samples
+    pub interval_ms: u64,
+}
+impl Default for HistoryConfig {
+    fn default() -> Self {
+        Self {
+            length: 60,
+            interval_ms: 1000,
+        }
+    }
+}
+
+/// How the hardware numbers are written in the strings published next to them
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct FormatConfig {
+    /// digits after the decimal point
+    pub precision: usize,
+    pub units: Units,
+}
+impl Default for FormatConfig {
+    fn default() -> Self {
+        Self {
+            precision: 1,
+            units: Units::Si,
+        }
+    }
+}
+#[derive(Debug, Clone, Copy, Default, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Units {
+    /// powers of 1000, e.g. `kB`
+    #[default]
+    Si,
+    /// powers of 1024, e.g. `KiB`
+    Iec,
+}
+
+/// The hwmon sensors published to the `Sensors` global
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct SensorsConfig {
+    /// the sensors to publish.  Empty means all of them
+    pub select: Vec<SensorSelector>,
+}
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct SensorSelector {
+    /// glob pattern of the chip, e.g. `k10temp` or `nvme`
+    pub chip: String,
+    /// glob pattern of the label, e.g. `Tctl`, or the input like `temp1` for unlabeled ones
+    pub label: String,
+}
+impl Default for SensorSelector {
+    fn default() -> Self {
+        Self {
+            chip: "*".to_owned(),
+            label: "*".to_owned(),
+        }
+    }
+}
+
+/// When the battery counts as low or critical, and what to do about it
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct BatteryConfig {
+    /// charge in percent at or below which a discharging battery is low
+    pub low: f64,
+    /// charge in percent at or below which a discharging battery is critical
+    pub critical: f64,
+    /// shell command run when the battery becomes low.  `EMBARGO_BATTERY` holds the charge.
+    pub low_action: Option<String>,
+    /// shell command run when the battery becomes critical, e.g. `systemctl hibernate`
+    pub critical_action: Option<String>,
+}
+impl Default for BatteryConfig {
+    fn default() -> Self {
+        Self {
+            low: 20.0,
+            critical: 5.0,
+            low_action: None,
+            critical_action: None,
+        }
+    }
+}
+
+/// The filesystems and block devices published as `disks` and `disk_io`
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct DisksConfig {
+    /// glob patterns of the mount points to report the space of, e.g. `/` or `/home`.  Empty
+    /// means all of them
+    pub mount_points: Vec<String>,
+    /// glob patterns of the block devices to report the throughput of, e.g. `nvme0n1`.  Empty
+    /// means every physical disk
+    pub devices: Vec<String>,
+}
+
+/// The processes published to the `Processes` global while its `shown` property is true
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct ProcessesConfig {
+    /// how many of the processes using the most cpu and memory are listed
+    pub count: usize,
+}
+impl Default for ProcessesConfig {
+    fn default() -> Self {
+        Self { count: 5 }
+    }
+}
+
+/// Written as the name of the interface or `auto`
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(from = "String", into = "String")]
+pub enum NetworkInterface {
+    /// whichever interface carries the default route
+    Auto,
+    Named(String),
+}
+impl From<String> for NetworkInterface {
+    fn from(name: String) -> Self {
+        if name == "auto" {
+            Self::Auto
+        } else {
+            Self::Named(name)
+        }
+    }
+}
+impl From<NetworkInterface> for String {
+    fn from(interface: NetworkInterface) -> Self {
+        match interface {
+            NetworkInterface::Auto => "auto".to_owned(),
+            NetworkInterface::Named(name) => name,
+        }
+    }
+}
+
 impl ConfigFile {
     pub fn generate_default(path: &Path) -> anyhow::Result<()> {
         let config = toml::to_string_pretty(&Self::default())?;
@@ -93,25 +373,46 @@ impl ConfigFile {
 impl Default for ConfigFile {
     fn default() -> Self {
         Self {
-            layer_name: clap::crate_name!().to_string(),
-            anchor: SimpleAnchor::Top,
+            bar: BarFile::default(),
+            bars: Vec::new(),
             scripts: vec![("example_date".to_string(), Script::example())]
                 .into_iter()
                 .collect::<HashMap<_, _>>(),
+            osd: OsdConfig::default(),
+            network_interface: NetworkInterface::Auto,
+            history: HistoryConfig::default(),
+            format: FormatConfig::default(),
+            sensors: SensorsConfig::default(),
+            battery: BatteryConfig::default(),
+            disks: DisksConfig::default(),
+            processes: ProcessesConfig::default(),
+            sysfs_root: PathBuf::from("/sys"),
+            procfs_root: PathBuf::from("/proc"),
+        }
+    }
+}
+impl Default for BarFile {
+    fn default() -> Self {
+        Self {
+            layer_name: clap::crate_name!().to_string(),
+            anchor: SimpleAnchor::Top,
+            outputs: Vec::new(),
+            exclude_outputs: Vec::new(),
+            primary_only: false,
+            width: 1920,
+            height: 40,
+            auto_hide: None,
+            input_region: SimpleInputRegion::Full,
             slint_entrypoint: None,
         }
     }
 }
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
 enum SimpleAnchor {
+    #[default]
     Top,
     Bottom,
 }
-impl Default for SimpleAnchor {
-    fn default() -> Self {
-        Self::Top
-    }
-}
 impl From<SimpleAnchor> for Anchor {
     fn from(val: SimpleAnchor) -> Self {
         match val {
@@ -120,3 +421,30 @@ impl From<SimpleAnchor> for Anchor {
         }
     }
 }
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+enum SimpleInputRegion {
+    #[default]
+    Full,
+    Opaque,
+    /// `[x, y, width, height]` in surface coordinates
+    Rects(Vec<[i32; 4]>),
+}
+impl From<SimpleInputRegion> for InputRegion {
+    fn from(val: SimpleInputRegion) -> Self {
+        match val {
+            SimpleInputRegion::Full => InputRegion::Full,
+            SimpleInputRegion::Opaque => InputRegion::Opaque,
+            SimpleInputRegion::Rects(rects) => InputRegion::Rects(
+                rects
+                    .into_iter()
+                    .map(|[x, y, width, height]| Rect {
+                        x,
+                        y,
+                        width,
+                        height,
+                    })
+                    .collect(),
+            ),
+        }
+    }
+}
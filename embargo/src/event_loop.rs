@@ -0,0 +1,96 @@
+use std::{
+    io::{ErrorKind, Read, Write},
+    os::{fd::AsRawFd, unix::net::UnixStream},
+    sync::Arc,
+    time::Duration,
+};
+
+use layer_platform::{Bar, EventQueue};
+
+/// Wakes the main loop from other threads, e.g. when a script finished or a message arrived
+#[derive(Clone)]
+pub struct Waker(Arc<UnixStream>);
+
+impl Waker {
+    pub fn wake(&self) {
+        // a full pipe already wakes the loop
+        let _ = (&*self.0).write(&[0]);
+    }
+}
+
+impl AsRawFd for Waker {
+    fn as_raw_fd(&self) -> std::os::fd::RawFd {
+        self.0.as_raw_fd()
+    }
+}
+
+/// The side of a [`Waker`] the main loop waits on
+pub struct WakeReceiver(UnixStream);
+
+impl WakeReceiver {
+    pub fn new() -> anyhow::Result<(Waker, Self)> {
+        let (sender, receiver) = UnixStream::pair()?;
+        sender.set_nonblocking(true)?;
+        receiver.set_nonblocking(true)?;
+        Ok((Waker(Arc::new(sender)), Self(receiver)))
+    }
+    fn drain(&mut self) {
+        let mut buf = [0; 64];
+        loop {
+            match self.0.read(&mut buf) {
+                Ok(0) => return,
+                Ok(_) => {}
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(_) => return,
+            }
+        }
+    }
+}
+
+/// Like [`EventQueue::blocking_dispatch`], but also returns when `wake` is woken or `timeout`
+/// passed
+pub fn dispatch(
+    event_queue: &mut EventQueue,
+    bar: &mut Bar,
+    wake: &mut WakeReceiver,
+    timeout: Option<Duration>,
+) -> anyhow::Result<()> {
+    if event_queue.dispatch_pending(bar)? > 0 {
+        return Ok(());
+    }
+    event_queue.flush()?;
+    let guard = event_queue.prepare_read()?;
+    let mut fds = [
+        libc::pollfd {
+            fd: guard.connection_fd().as_raw_fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        },
+        libc::pollfd {
+            fd: wake.0.as_raw_fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        },
+    ];
+    let timeout = timeout.map_or(-1, |t| {
+        i32::try_from(t.as_millis().max(1)).unwrap_or(i32::MAX)
+    });
+    // SAFETY: `fds` is a valid array of pollfd for the duration of the call
+    let ready = unsafe { libc::poll(fds.as_mut_ptr(), fds.len() as libc::nfds_t, timeout) };
+    if ready < 0 {
+        let error = std::io::Error::last_os_error();
+        if error.kind() != ErrorKind::Interrupted {
+            return Err(error.into());
+        }
+    }
+    if fds[0].revents == 0 {
+        drop(guard);
+    } else {
+        guard.read()?;
+    }
+    if fds[1].revents != 0 {
+        wake.drain();
+    }
+    event_queue.dispatch_pending(bar)?;
+    Ok(())
+}
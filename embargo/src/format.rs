@@ -0,0 +1,95 @@
+//! Turns the raw hardware numbers into the strings shown in the bar, as configured under
+//! `[format]`
+use std::time::Duration;
+
+use crate::config::{FormatConfig, Units};
+
+impl FormatConfig {
+    /// A size like `1.5GB`, or `1.4GiB` with IEC units
+    pub fn bytes(&self, bytes: f64) -> String {
+        let (divisor, prefixes) = match self.units {
+            Units::Si => (1000.0, ["", "k", "M", "G", "T", "P", "E"]),
+            Units::Iec => (1024.0, ["", "Ki", "Mi", "Gi", "Ti", "Pi", "Ei"]),
+        };
+        let mut value = bytes;
+        let mut prefix = 0;
+        while value.abs() >= divisor && prefix < prefixes.len() - 1 {
+            value /= divisor;
+            prefix += 1;
+        }
+        // there are no fractions of a byte
+        let precision = if prefix == 0 { 0 } else { self.precision };
+        format!("{value:.precision$}{}B", prefixes[prefix])
+    }
+    /// A throughput like `1.5MB/s`
+    pub fn rate(&self, bytes_per_second: f64) -> String {
+        format!("{}/s", self.bytes(bytes_per_second))
+    }
+    /// A frequency like `3.4GHz`, given in MHz
+    pub fn frequency(&self, mhz: f64) -> String {
+        if mhz >= 1000.0 {
+            format!("{:.*}GHz", self.precision, mhz / 1000.0)
+        } else {
+            format!("{mhz:.0}MHz")
+        }
+    }
+    /// A temperature like `45.5°C`
+    pub fn temperature(&self, celsius: f64) -> String {
+        format!("{celsius:.*}°C", self.precision)
+    }
+    /// A power like `12.5W`
+    pub fn power(&self, watts: f64) -> String {
+        format!("{watts:.*}W", self.precision)
+    }
+    /// A duration like `3d 04h`, `2h 05m` or `45m`
+    pub fn duration(duration: Duration) -> String {
+        let minutes = duration.as_secs() / 60;
+        let hours = minutes / 60;
+        match (hours / 24, hours) {
+            (0, 0) => format!("{minutes}m"),
+            (0, hours) => format!("{hours}h {:02}m", minutes % 60),
+            (days, hours) => format!("{days}d {:02}h", hours % 24),
+        }
+    }
+    /// A plain number like `0.52`
+    pub fn number(&self, number: f64) -> String {
+        format!("{number:.*}", self.precision)
+    }
+    /// A percentage like `12.5`, without the sign
+    pub fn percent(&self, percent: f64) -> String {
+        format!("{percent:.*}", self.precision)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn units() {
+        let si = FormatConfig {
+            precision: 1,
+            units: Units::Si,
+        };
+        assert_eq!(si.bytes(999.0), "999B");
+        assert_eq!(si.bytes(1_500_000.0), "1.5MB");
+        assert_eq!(si.rate(2048.0), "2.0kB/s");
+        assert_eq!(si.percent(12.345), "12.3");
+        assert_eq!(si.frequency(3400.0), "3.4GHz");
+        assert_eq!(si.frequency(800.0), "800MHz");
+        assert_eq!(si.temperature(45.25), "45.2°C");
+        assert_eq!(si.power(9.87), "9.9W");
+        assert_eq!(FormatConfig::duration(Duration::from_mins(45)), "45m");
+        assert_eq!(FormatConfig::duration(Duration::from_mins(125)), "2h 05m");
+        assert_eq!(FormatConfig::duration(Duration::from_hours(76)), "3d 04h");
+
+        let iec = FormatConfig {
+            precision: 2,
+            units: Units::Iec,
+        };
+        assert_eq!(iec.bytes(2048.0), "2.00KiB");
+        assert_eq!(iec.bytes(1_500_000_000.0), "1.40GiB");
+        assert_eq!(iec.percent(50.0), "50.00");
+        assert_eq!(iec.number(0.5), "0.50");
+    }
+}
@@ -1,102 +1,665 @@
-use std::time::{Duration, Instant};
-use sysinfo::{CpuExt, NetworkExt, SystemExt};
+use std::{
+    path::PathBuf,
+    process::Command,
+    time::{Duration, Instant},
+};
+use sysinfo::{CpuExt, CpuRefreshKind, NetworkExt, ProcessRefreshKind, SystemExt};
 
 use sysinfo::System;
 
+use crate::config::{BatteryConfig, HardwareConfig, NetworkInterface};
+
+pub mod disks;
+pub mod history;
+pub mod network;
+pub mod power;
+pub mod processes;
+pub mod route;
+pub mod sensors;
+pub mod stats;
+
+/// How often the default route is looked up when following it
+const ROUTE_REFRESH_FREQUENCY: Duration = Duration::from_secs(2);
+
 pub struct HardwareMonitor {
     system: System,
-    modules: Vec<HardwareModule>,
-    interface_name: String,
+    cpu_module: HardwareModule,
+    memory_module: HardwareModule,
+    networks_list_module: HardwareModule,
+    disks_list_module: HardwareModule,
+    disks_module: HardwareModule,
+    /// only updated while the processes are shown, since listing them is expensive
+    processes_module: HardwareModule,
+    users_module: HardwareModule,
+    processes_shown: bool,
+    process_count: usize,
+    /// as of the last process refresh
+    top_processes: processes::Top,
+    disk_io_module: HardwareModule<disks::Throughput>,
+    disk_io: disks::Throughput,
+    /// glob patterns of the mount points whose space is reported
+    mount_points: Vec<String>,
+    /// the selected filesystems as of the last disk refresh
+    disks: Vec<disks::Disk>,
+    interface: NetworkInterface,
+    /// the interface traffic is reported for, or why there is none
+    interface_name: Result<String, String>,
+    /// when the default route was last looked up
+    last_route_check: Option<Instant>,
+    /// every interface as of the last network refresh, sorted by name
+    interfaces: Vec<network::Interface>,
+    last_network_refresh: Option<Instant>,
+    procfs: PathBuf,
+    sysfs: PathBuf,
     network_refresh_frequency: Duration,
+    histories: Histories,
+    history_interval: Duration,
+    last_history_sample: Option<Instant>,
+    /// the selected sensors, or why they couldn't be read
+    sensors: Result<Vec<sensors::Sensor>, String>,
+    sensors_module: HardwareModule<Result<Vec<sensors::Sensor>, String>>,
+    /// the batteries and AC adapters, or why they couldn't be read
+    power: Result<power::PowerSupplies, String>,
+    battery: BatteryConfig,
+    battery_alerts: power::Alerts,
+    power_module: HardwareModule<Result<power::PowerSupplies, String>>,
+    /// the cached memory and process count, or why they couldn't be read
+    stats: Result<stats::Stats, String>,
+    stats_module: HardwareModule<Result<stats::Stats, String>>,
+    /// whether the next update reports everything as refreshed
+    refresh_all: bool,
+}
+
+/// What a call to [`HardwareMonitor::update`] refreshed.  Only that needs to be published
+/// again: a new model never equals the previous one, so publishing it every frame would redraw
+/// every bar every frame.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[allow(clippy::struct_excessive_bools)] // one per source, and they refresh independently
+pub struct Refreshed {
+    pub histories: bool,
+    /// the interfaces and their rates
+    pub network: bool,
+    /// the usage of all cpus and each core
+    pub cpu: bool,
+    pub sensors: bool,
+    /// the batteries and AC adapters
+    pub power: bool,
+    /// the space on the filesystems
+    pub disks: bool,
+    /// the throughput of the block devices
+    pub disk_io: bool,
+    /// memory and swap
+    pub memory: bool,
+    /// the cached memory and the process count
+    pub stats: bool,
+    /// the top processes, only while they are shown
+    pub processes: bool,
+}
+
+impl Refreshed {
+    /// Everything, for uis that nothing was published to yet
+    pub fn all() -> Self {
+        Self {
+            histories: true,
+            network: true,
+            cpu: true,
+            sensors: true,
+            power: true,
+            disks: true,
+            disk_io: true,
+            memory: true,
+            stats: true,
+            processes: true,
+        }
+    }
+}
+
+/// A logical cpu
+pub struct Core {
+    pub id: usize,
+    /// usage in percent
+    pub usage: f32,
+    /// current frequency in MHz
+    pub frequency: u64,
+}
+
+/// The recent values of the metrics that can be graphed
+#[derive(Default)]
+pub struct Histories {
+    /// usage in percent
+    pub cpu: history::History,
+    /// used memory in percent
+    pub memory: history::History,
+    /// bytes per second on the monitored interface
+    pub network_up: history::History,
+    pub network_down: history::History,
 }
 
 impl HardwareMonitor {
-    pub fn new(interface_name: String) -> Self {
+    pub fn new(config: &HardwareConfig) -> Self {
         let network_refresh_frequency = Duration::from_secs(2);
-        let modules = vec![
-            HardwareModule::new(Duration::from_millis(500), Box::new(System::refresh_cpu)),
-            HardwareModule::new(
-                Duration::from_millis(1000),
-                Box::new(System::refresh_memory),
+        let mut monitor = Self {
+            system: System::new(),
+            // everything, so the frequency of each core is refreshed along with its usage
+            cpu_module: HardwareModule::new(
+                Duration::from_millis(500),
+                Box::new(|system| system.refresh_cpu_specifics(CpuRefreshKind::everything())),
             ),
-            HardwareModule::new(
-                network_refresh_frequency,
-                Box::new(System::refresh_networks),
+            memory_module: HardwareModule::new(
+                Duration::from_secs(1),
+                Box::new(System::refresh_memory),
             ),
-            HardwareModule::new(
+            networks_list_module: HardwareModule::new(
                 Duration::from_secs(10),
                 Box::new(System::refresh_networks_list),
             ),
-        ];
-        Self {
-            system: System::new(),
-            modules,
+            // mounts come and go rarely, unlike free space
+            disks_list_module: HardwareModule::new(
+                Duration::from_secs(30),
+                Box::new(System::refresh_disks_list),
+            ),
+            disks_module: HardwareModule::new(
+                Duration::from_secs(5),
+                Box::new(System::refresh_disks),
+            ),
+            // disk usage isn't shown
+            processes_module: HardwareModule::new(
+                Duration::from_secs(2),
+                Box::new(|system| {
+                    system.refresh_processes_specifics(
+                        ProcessRefreshKind::new().with_cpu().with_user(),
+                    );
+                }),
+            ),
+            users_module: HardwareModule::new(
+                Duration::from_mins(1),
+                Box::new(System::refresh_users_list),
+            ),
+            processes_shown: false,
+            process_count: 0,
+            top_processes: processes::Top::default(),
+            disk_io_module: HardwareModule::new(
+                Duration::from_secs(1),
+                Box::new(disks::Throughput::refresh),
+            ),
+            disk_io: disks::Throughput::new(config.procfs_root.clone(), config.sysfs_root.clone()),
+            mount_points: Vec::new(),
+            disks: Vec::new(),
             network_refresh_frequency,
-            interface_name,
+            interface_name: Err("no default route".to_owned()),
+            interface: NetworkInterface::Auto,
+            last_route_check: None,
+            interfaces: Vec::new(),
+            last_network_refresh: None,
+            procfs: config.procfs_root.clone(),
+            sysfs: config.sysfs_root.clone(),
+            histories: Histories::default(),
+            history_interval: Duration::ZERO,
+            last_history_sample: None,
+            sensors: Ok(Vec::new()),
+            sensors_module: sensors_module(config),
+            power: Ok(power::PowerSupplies::default()),
+            battery: BatteryConfig::default(),
+            battery_alerts: power::Alerts::default(),
+            power_module: power_module(config),
+            stats: Ok(stats::Stats::default()),
+            stats_module: stats_module(config),
+            refresh_all: false,
+        };
+        monitor.configure(config);
+        monitor
+    }
+    /// Apply a new config, e.g. after a reload.  The histories are kept.
+    pub fn configure(&mut self, config: &HardwareConfig) {
+        self.set_interface(config.network_interface.clone());
+        let histories = &mut self.histories;
+        for history in [
+            &mut histories.cpu,
+            &mut histories.memory,
+            &mut histories.network_up,
+            &mut histories.network_down,
+        ] {
+            history.set_length(config.history.length);
         }
+        self.history_interval = Duration::from_millis(config.history.interval_ms);
+        self.sysfs.clone_from(&config.sysfs_root);
+        // a new one reads the sensors on the next update
+        self.sensors_module = sensors_module(config);
+        self.battery.clone_from(&config.battery);
+        // a new one reads them on the next update, against the new thresholds
+        self.power_module = power_module(config);
+        self.procfs.clone_from(&config.procfs_root);
+        self.stats_module = stats_module(config);
+        self.process_count = config.processes.count;
+        self.top_processes = processes::top(processes::list(&self.system), self.process_count);
+        self.mount_points.clone_from(&config.disks.mount_points);
+        self.disks = disks::usage(&self.system, &self.mount_points);
+        self.disk_io.select.clone_from(&config.disks.devices);
+        self.disk_io.sysfs.clone_from(&config.sysfs_root);
+        self.disk_io.procfs.clone_from(&config.procfs_root);
     }
-    pub fn update(&mut self) {
-        for module in &mut self.modules {
-            module.update(&mut self.system);
+    /// Report traffic for `interface` from now on
+    fn set_interface(&mut self, interface: NetworkInterface) {
+        if let NetworkInterface::Named(name) = &interface {
+            self.interface_name = Ok(name.clone());
         }
+        self.interface = interface;
+        self.last_route_check = None;
+    }
+    pub fn update(&mut self) -> Refreshed {
+        let mut refreshed = Refreshed::default();
+        if self.interface == NetworkInterface::Auto
+            && self
+                .last_route_check
+                .is_none_or(|t| t.elapsed() > ROUTE_REFRESH_FREQUENCY)
+        {
+            self.last_route_check = Some(Instant::now());
+            // the rates are for another interface now
+            refreshed.network |= self.follow_default_route();
+        }
+        refreshed.cpu = self.cpu_module.update(&mut self.system);
+        refreshed.memory = self.memory_module.update(&mut self.system);
+        self.networks_list_module.update(&mut self.system);
+        let disks_listed = self.disks_list_module.update(&mut self.system);
+        if self.disks_module.update(&mut self.system) || disks_listed {
+            self.disks = disks::usage(&self.system, &self.mount_points);
+            refreshed.disks = true;
+        }
+        refreshed.disk_io = self.disk_io_module.update(&mut self.disk_io);
+        if self.processes_shown {
+            self.users_module.update(&mut self.system);
+            // the first cpu usage after being hidden is the average over that time
+            if self.processes_module.update(&mut self.system) {
+                self.top_processes =
+                    processes::top(processes::list(&self.system), self.process_count);
+                refreshed.processes = true;
+            }
+        }
+        if self
+            .last_network_refresh
+            .is_none_or(|t| t.elapsed() > self.network_refresh_frequency)
+        {
+            self.system.refresh_networks();
+            self.sample_networks();
+            refreshed.network = true;
+        }
+        if self
+            .last_history_sample
+            .is_none_or(|t| t.elapsed() >= self.history_interval)
+        {
+            self.last_history_sample = Some(Instant::now());
+            self.sample_histories();
+            refreshed.histories = true;
+        }
+        refreshed.sensors = self.sensors_module.update(&mut self.sensors);
+        if self.power_module.update(&mut self.power) {
+            self.check_battery();
+            refreshed.power = true;
+        }
+        refreshed.stats = self.stats_module.update(&mut self.stats);
+        if std::mem::take(&mut self.refresh_all) {
+            return Refreshed::all();
+        }
+        refreshed
+    }
+    /// Report everything as refreshed on the next update, e.g. for new uis that nothing was
+    /// published to yet
+    pub fn mark_all_refreshed(&mut self) {
+        self.refresh_all = true;
+    }
+    /// Run the configured action when the battery just became low or critical
+    fn check_battery(&mut self) {
+        let Ok(power) = &self.power else {
+            return;
+        };
+        let Some(capacity) = power.capacity() else {
+            return;
+        };
+        let action = match self.battery_alerts.check(self.battery_level()) {
+            Some(power::Level::Low) => &self.battery.low_action,
+            Some(power::Level::Critical) => &self.battery.critical_action,
+            Some(power::Level::Normal) | None => return,
+        };
+        tracing::warn!("the battery is at {capacity:.0}%");
+        let Some(action) = action.clone() else {
+            return;
+        };
+        std::thread::spawn(move || {
+            let status = Command::new("sh")
+                .arg("-c")
+                .arg(&action)
+                .env("EMBARGO_BATTERY", format!("{capacity:.0}"))
+                .status();
+            match status {
+                Ok(status) if status.success() => {}
+                Ok(status) => tracing::warn!("battery action '{action}' exited with {status}"),
+                Err(e) => tracing::warn!("failed to run battery action '{action}': {e}"),
+            }
+        });
+    }
+    /// How low the batteries are.  Only discharging batteries can be low.
+    pub fn battery_level(&self) -> power::Level {
+        match &self.power {
+            Ok(power) if power.discharging() => match power.capacity() {
+                Some(capacity) if capacity <= self.battery.critical => power::Level::Critical,
+                Some(capacity) if capacity <= self.battery.low => power::Level::Low,
+                _ => power::Level::Normal,
+            },
+            _ => power::Level::Normal,
+        }
+    }
+    #[allow(clippy::cast_precision_loss, clippy::cast_possible_truncation)]
+    fn sample_histories(&mut self) {
+        let memory = self.used_mem() as f32 / self.total_mem().max(1) as f32 * 100.0;
+        let up = self.uploaded_bytes().unwrap_or_default() as f32;
+        let down = self.downloaded_bytes().unwrap_or_default() as f32;
+        self.histories.cpu.push(self.cpu_usage());
+        self.histories.memory.push(memory);
+        self.histories.network_up.push(up);
+        self.histories.network_down.push(down);
+    }
+    /// Compute the rates of every interface since the last network refresh
+    fn sample_networks(&mut self) {
+        let now = Instant::now();
+        let elapsed = self
+            .last_network_refresh
+            .replace(now)
+            .map(|t| now.duration_since(t).as_secs_f64());
+        let mut addresses = network::addresses()
+            .inspect_err(|e| tracing::warn!("failed to get the ip addresses: {e}"))
+            .unwrap_or_default();
+        let mut interfaces = self
+            .system
+            .networks()
+            .into_iter()
+            .map(|(name, data)| {
+                let previous = self.interfaces.iter().find(|i| i.name == *name);
+                network::Interface {
+                    up: rate(
+                        data.total_transmitted(),
+                        previous.map(|i| i.total_up),
+                        elapsed,
+                    ),
+                    down: rate(
+                        data.total_received(),
+                        previous.map(|i| i.total_down),
+                        elapsed,
+                    ),
+                    total_up: data.total_transmitted(),
+                    total_down: data.total_received(),
+                    state: network::operational_state(&self.sysfs, name),
+                    addresses: addresses.remove(name).unwrap_or_default(),
+                    name: name.clone(),
+                }
+            })
+            .collect::<Vec<_>>();
+        interfaces.sort_by(|a, b| a.name.cmp(&b.name));
+        self.interfaces = interfaces;
+    }
+    /// Look up the default route, and tell whether it moved to another interface
+    fn follow_default_route(&mut self) -> bool {
+        let interface_name = match route::default_interface(&self.procfs) {
+            Ok(Some(name)) => Ok(name),
+            Ok(None) => Err("no default route".to_owned()),
+            Err(e) => Err(format!("failed to read the routing table: {e}")),
+        };
+        if interface_name == self.interface_name {
+            return false;
+        }
+        if let Ok(name) = &interface_name {
+            tracing::info!("following the default route to '{name}'");
+            // a new interface, e.g. wifi that just connected, isn't known yet
+            if !self.system.networks().into_iter().any(|(n, _)| n == name) {
+                self.system.refresh_networks_list();
+            }
+        }
+        self.interface_name = interface_name;
+        true
     }
     pub fn cpu_usage(&self) -> f32 {
         self.system.global_cpu_info().cpu_usage()
     }
+    /// Every logical cpu, ordered by id
+    pub fn cores(&self) -> impl Iterator<Item = Core> + '_ {
+        self.system.cpus().iter().enumerate().map(|(id, cpu)| Core {
+            id,
+            usage: cpu.cpu_usage(),
+            frequency: cpu.frequency(),
+        })
+    }
     pub fn used_mem(&self) -> u64 {
         self.system.used_memory()
     }
     pub fn total_mem(&self) -> u64 {
         self.system.total_memory()
     }
-    pub fn uploaded_bytes(&self) -> u64 {
-        if let Some(bytes) = self
-            .system
-            .networks()
-            .into_iter()
-            .find(|(name, _)| **name == self.interface_name)
-            .map(|(_, network)| network.transmitted())
-        {
-            bytes / self.network_refresh_frequency.as_secs()
-        } else {
-            tracing::warn!("unable to locate interface '{}'", self.interface_name);
-            0
-        }
+    /// Memory that can be given to programs without swapping, including most of the cache
+    pub fn available_mem(&self) -> u64 {
+        self.system.available_memory()
     }
-    pub fn downloaded_bytes(&self) -> u64 {
-        if let Some(bytes) = self
-            .system
-            .networks()
-            .into_iter()
-            .find(|(name, _)| **name == self.interface_name)
-            .map(|(_, network)| network.received())
-        {
-            bytes / self.network_refresh_frequency.as_secs()
-        } else {
-            tracing::warn!("unable to locate interface '{}'", self.interface_name);
-            0
-        }
+    /// Memory used by buffers and the page cache
+    pub fn cached_mem(&self) -> anyhow::Result<u64> {
+        Ok(self.stats()?.cached_memory)
+    }
+    pub fn used_swap(&self) -> u64 {
+        self.system.used_swap()
+    }
+    pub fn total_swap(&self) -> u64 {
+        self.system.total_swap()
+    }
+    /// The number of processes waiting for the cpu averaged over 1, 5 and 15 minutes
+    pub fn load_average(&self) -> sysinfo::LoadAvg {
+        self.system.load_average()
+    }
+    pub fn uptime(&self) -> Duration {
+        Duration::from_secs(self.system.uptime())
+    }
+    /// How many processes there are
+    pub fn processes(&self) -> anyhow::Result<usize> {
+        Ok(self.stats()?.processes)
+    }
+    fn stats(&self) -> anyhow::Result<&stats::Stats> {
+        self.stats.as_ref().map_err(|e| anyhow::anyhow!("{e}"))
+    }
+    /// Bytes sent per second on the monitored interface
+    pub fn uploaded_bytes(&self) -> anyhow::Result<f64> {
+        Ok(self.network()?.up)
+    }
+    /// Bytes received per second on the monitored interface
+    pub fn downloaded_bytes(&self) -> anyhow::Result<f64> {
+        Ok(self.network()?.down)
+    }
+    pub fn histories(&self) -> &Histories {
+        &self.histories
+    }
+    /// The batteries and AC adapters
+    pub fn power(&self) -> anyhow::Result<&power::PowerSupplies> {
+        self.power.as_ref().map_err(|e| anyhow::anyhow!("{e}"))
+    }
+    /// The selected temperature and fan sensors
+    pub fn sensors(&self) -> anyhow::Result<&[sensors::Sensor]> {
+        self.sensors.as_deref().map_err(|e| anyhow::anyhow!("{e}"))
+    }
+    /// Whether the processes are shown somewhere.  They are only refreshed while they are.
+    pub fn set_processes_shown(&mut self, shown: bool) {
+        self.processes_shown = shown;
+    }
+    pub fn processes_shown(&self) -> bool {
+        self.processes_shown
+    }
+    /// The processes using the most cpu and memory as of the last time they were shown
+    pub fn top_processes(&self) -> &processes::Top {
+        &self.top_processes
+    }
+    /// Space on the selected filesystems, sorted by mount point
+    pub fn disks(&self) -> &[disks::Disk] {
+        &self.disks
+    }
+    /// Throughput of the selected block devices, sorted by name
+    pub fn disk_io(&self) -> anyhow::Result<&[disks::BlockDevice]> {
+        self.disk_io.devices().map_err(|e| anyhow::anyhow!("{e}"))
+    }
+    /// Every network interface, sorted by name
+    pub fn interfaces(&self) -> &[network::Interface] {
+        &self.interfaces
+    }
+    fn network(&self) -> anyhow::Result<&network::Interface> {
+        let interface_name = self
+            .interface_name
+            .as_ref()
+            .map_err(|e| anyhow::anyhow!("{e}"))?;
+        self.interfaces
+            .iter()
+            .find(|i| i.name == *interface_name)
+            .ok_or_else(|| anyhow::anyhow!("unable to locate interface '{interface_name}'"))
     }
 }
 
-pub struct HardwareModule {
+/// Refreshes `T`, the sysinfo `System` unless it is something sysinfo doesn't cover, every
+/// `frequency`
+pub struct HardwareModule<T = System> {
     last_update: Instant,
     frequency: Duration,
-    refresh: Box<dyn Fn(&mut System)>,
+    refresh: Box<dyn Fn(&mut T)>,
 }
 
-impl HardwareModule {
-    pub fn new(frequency: Duration, refresh: Box<dyn Fn(&mut System)>) -> Self {
+impl<T> HardwareModule<T> {
+    pub fn new(frequency: Duration, refresh: Box<dyn Fn(&mut T)>) -> Self {
         Self {
             last_update: Instant::now().checked_sub(frequency).unwrap(),
             frequency,
             refresh,
         }
     }
-    pub fn update(&mut self, system: &mut System) {
-        if self.last_update.elapsed() > self.frequency {
+    /// Refresh `target` if it is due, and tell whether it was
+    pub fn update(&mut self, target: &mut T) -> bool {
+        let due = self.last_update.elapsed() > self.frequency;
+        if due {
             self.last_update = Instant::now();
-            (self.refresh)(system);
+            (self.refresh)(target);
         }
+        due
+    }
+}
+
+/// Reads the sensors selected in `config`
+fn sensors_module(config: &HardwareConfig) -> HardwareModule<Result<Vec<sensors::Sensor>, String>> {
+    let (sysfs, select) = (config.sysfs_root.clone(), config.sensors.select.clone());
+    HardwareModule::new(
+        Duration::from_secs(2),
+        Box::new(move |sensors| {
+            *sensors = sensors::read(&sysfs, &select)
+                .map_err(|e| format!("failed to read the hwmon sensors: {e}"));
+        }),
+    )
+}
+
+/// Reads the batteries and AC adapters
+fn power_module(config: &HardwareConfig) -> HardwareModule<Result<power::PowerSupplies, String>> {
+    let sysfs = config.sysfs_root.clone();
+    HardwareModule::new(
+        Duration::from_secs(2),
+        Box::new(move |power| {
+            *power =
+                power::read(&sysfs).map_err(|e| format!("failed to read the power supplies: {e}"));
+        }),
+    )
+}
+
+/// Reads the cached memory and the process count
+fn stats_module(config: &HardwareConfig) -> HardwareModule<Result<stats::Stats, String>> {
+    let procfs = config.procfs_root.clone();
+    HardwareModule::new(
+        Duration::from_secs(2),
+        Box::new(move |stats| {
+            *stats =
+                stats::read(&procfs).map_err(|e| format!("failed to read the kernel stats: {e}"));
+        }),
+    )
+}
+
+/// The bytes per second of a counter that was at `previous` `elapsed` seconds ago.  That is the
+/// time that actually passed, which is longer than the refresh frequency when the loop runs late.
+/// Counters start over when their interface or device is recreated, which counts as no traffic.
+#[allow(clippy::cast_precision_loss)]
+pub fn rate(total: u64, previous: Option<u64>, elapsed: Option<f64>) -> f64 {
+    match (elapsed, previous) {
+        (Some(elapsed), Some(previous)) if elapsed > 0.0 => {
+            total.saturating_sub(previous) as f64 / elapsed
+        }
+        _ => 0.0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::temp_dir::TempDir;
+
+    fn config() -> HardwareConfig {
+        HardwareConfig {
+            network_interface: NetworkInterface::Auto,
+            history: crate::config::HistoryConfig::default(),
+            format: crate::config::FormatConfig::default(),
+            sensors: crate::config::SensorsConfig::default(),
+            battery: BatteryConfig::default(),
+            disks: crate::config::DisksConfig::default(),
+            processes: crate::config::ProcessesConfig::default(),
+            sysfs_root: PathBuf::from("/sys"),
+            procfs_root: PathBuf::from("/proc"),
+        }
+    }
+
+    #[test]
+    fn rates() {
+        assert!((rate(3000, Some(1000), Some(1.0)) - 2000.0).abs() < f64::EPSILON);
+        // the loop ran late, so twice the bytes came in twice the time
+        assert!((rate(5000, Some(1000), Some(2.0)) - 2000.0).abs() < f64::EPSILON);
+        // the counter started over
+        assert!(rate(200, Some(1000), Some(1.0)).abs() < f64::EPSILON);
+        // nothing to compare with yet
+        assert!(rate(1000, None, Some(1.0)).abs() < f64::EPSILON);
+        assert!(rate(1000, Some(0), None).abs() < f64::EPSILON);
+        assert!(rate(1000, Some(0), Some(0.0)).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn reports_refreshes() {
+        let mut hw_mon = HardwareMonitor::new(&config());
+        let refreshed = hw_mon.update();
+        assert!(refreshed.histories && refreshed.network && refreshed.cpu);
+        // the next sample is a second away
+        assert_eq!(hw_mon.update(), Refreshed::default());
+        hw_mon.mark_all_refreshed();
+        assert_eq!(hw_mon.update(), Refreshed::all());
+        assert_eq!(hw_mon.update(), Refreshed::default());
+    }
+
+    #[test]
+    fn follows_default_route() {
+        let procfs = TempDir::new("follow");
+        let set_default = |interface: &str| {
+            let route = format!(
+                "Iface\tDestination\tGateway \tFlags\tRefCnt\tUse\tMetric\tMask\t\tMTU\tWindow\tIRTT\n\
+                 {interface}\t00000000\t0101A8C0\t0003\t0\t0\t100\t00000000\t0\t0\t0\n"
+            );
+            procfs.write([("net/route", route)]);
+        };
+        let mut hw_mon = HardwareMonitor::new(&HardwareConfig {
+            procfs_root: procfs.to_path_buf(),
+            ..config()
+        });
+
+        set_default("enp6s0");
+        assert!(hw_mon.follow_default_route());
+        assert_eq!(hw_mon.interface_name.as_deref(), Ok("enp6s0"));
+        assert!(!hw_mon.follow_default_route());
+        set_default("wlan0");
+        assert!(hw_mon.follow_default_route());
+        assert_eq!(hw_mon.interface_name.as_deref(), Ok("wlan0"));
+
+        hw_mon.set_interface(NetworkInterface::Named("eth1".to_owned()));
+        hw_mon.update();
+        assert_eq!(hw_mon.interface_name.as_deref(), Ok("eth1"));
     }
 }
@@ -0,0 +1,220 @@
+//! Space on the mounted filesystems, and throughput of the block devices from `/proc/diskstats`
+use std::{path::PathBuf, time::Instant};
+
+use sysinfo::{DiskExt, System, SystemExt};
+
+use super::rate;
+
+/// diskstats counts in sectors of 512 bytes, whatever the sector size of the device
+const SECTOR_SIZE: u64 = 512;
+
+/// Space on a mounted filesystem
+#[derive(Debug, Clone, PartialEq)]
+pub struct Disk {
+    /// e.g. `/` or `/home`
+    pub mount_point: String,
+    /// e.g. `/dev/nvme0n1p2`
+    pub device: String,
+    /// e.g. `ext4`
+    pub file_system: String,
+    /// in bytes
+    pub total: u64,
+    pub used: u64,
+    /// bytes that can still be written, not counting the space reserved for root
+    pub free: u64,
+}
+
+impl Disk {
+    /// Used space in percent
+    #[allow(clippy::cast_precision_loss)]
+    pub fn usage(&self) -> f64 {
+        self.used as f64 / self.total.max(1) as f64 * 100.0
+    }
+}
+
+/// The filesystems sysinfo knows about whose mount point matches one of the glob patterns in
+/// `mount_points`, or all of them if it is empty, sorted by mount point
+pub fn usage(system: &System, mount_points: &[String]) -> Vec<Disk> {
+    let mut disks = system
+        .disks()
+        .iter()
+        .map(|disk| Disk {
+            mount_point: disk.mount_point().to_string_lossy().into_owned(),
+            device: disk.name().to_string_lossy().into_owned(),
+            file_system: String::from_utf8_lossy(disk.file_system()).into_owned(),
+            total: disk.total_space(),
+            used: disk.total_space().saturating_sub(disk.available_space()),
+            free: disk.available_space(),
+        })
+        .filter(|disk| {
+            mount_points.is_empty()
+                || mount_points
+                    .iter()
+                    .any(|pattern| layer_platform::glob_match(pattern, &disk.mount_point))
+        })
+        .collect::<Vec<_>>();
+    disks.sort_by(|a, b| a.mount_point.cmp(&b.mount_point));
+    // the same filesystem can be mounted twice, e.g. by a bind mount
+    disks.dedup_by(|a, b| a.mount_point == b.mount_point);
+    disks
+}
+
+/// The latest sample of a block device
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockDevice {
+    /// e.g. `nvme0n1` or `sda`
+    pub name: String,
+    /// bytes per second read since the previous sample
+    pub read: f64,
+    /// bytes per second written since the previous sample
+    pub write: f64,
+    pub total_read: u64,
+    pub total_written: u64,
+}
+
+/// Samples the throughput of the selected block devices
+#[derive(Debug)]
+pub struct Throughput {
+    pub procfs: PathBuf,
+    pub sysfs: PathBuf,
+    /// glob patterns of the devices to report.  Empty means every physical disk.
+    pub select: Vec<String>,
+    /// sorted by name, or why they couldn't be read
+    devices: Result<Vec<BlockDevice>, String>,
+    last_sample: Option<Instant>,
+}
+
+impl Throughput {
+    pub fn new(procfs: PathBuf, sysfs: PathBuf) -> Self {
+        Self {
+            procfs,
+            sysfs,
+            select: Vec::new(),
+            devices: Ok(Vec::new()),
+            last_sample: None,
+        }
+    }
+    pub fn refresh(&mut self) {
+        match std::fs::read_to_string(self.procfs.join("diskstats")) {
+            Ok(diskstats) => {
+                let counters = parse(&diskstats)
+                    .into_iter()
+                    .filter(|(name, _, _)| self.selected(name))
+                    .collect();
+                self.sample(counters, Instant::now());
+            }
+            Err(e) => {
+                self.devices = Err(format!("failed to read the disk stats: {e}"));
+                self.last_sample = None;
+            }
+        }
+    }
+    pub fn devices(&self) -> Result<&[BlockDevice], &str> {
+        self.devices.as_deref().map_err(String::as_str)
+    }
+    fn selected(&self, name: &str) -> bool {
+        if self.select.is_empty() {
+            // partitions, loop devices, zram and device mapper targets have no device of their own
+            self.sysfs.join("block").join(name).join("device").exists()
+        } else {
+            self.select
+                .iter()
+                .any(|pattern| layer_platform::glob_match(pattern, name))
+        }
+    }
+    /// Compute the rates of every device since the last sample, taken at `now`
+    fn sample(&mut self, mut counters: Vec<(String, u64, u64)>, now: Instant) {
+        let elapsed = self
+            .last_sample
+            .replace(now)
+            .map(|t| now.duration_since(t).as_secs_f64());
+        let previous = self.devices.as_deref().unwrap_or_default();
+        counters.sort();
+        let devices = counters
+            .into_iter()
+            .map(|(name, total_read, total_written)| {
+                let previous = previous.iter().find(|d| d.name == name);
+                BlockDevice {
+                    read: rate(total_read, previous.map(|d| d.total_read), elapsed),
+                    write: rate(total_written, previous.map(|d| d.total_written), elapsed),
+                    name,
+                    total_read,
+                    total_written,
+                }
+            })
+            .collect();
+        self.devices = Ok(devices);
+    }
+}
+
+/// `(name, bytes read, bytes written)` of every device in the contents of `/proc/diskstats`
+fn parse(diskstats: &str) -> Vec<(String, u64, u64)> {
+    diskstats
+        .lines()
+        .filter_map(|line| {
+            let fields = line.split_whitespace().collect::<Vec<_>>();
+            let sectors = |i: usize| fields.get(i)?.parse::<u64>().ok();
+            Some((
+                (*fields.get(2)?).to_owned(),
+                sectors(5)? * SECTOR_SIZE,
+                sectors(9)? * SECTOR_SIZE,
+            ))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::temp_dir::TempDir;
+
+    #[test]
+    fn diskstats() {
+        let root = TempDir::new("diskstats");
+        std::fs::create_dir_all(root.join("sys/block/nvme0n1/device")).unwrap();
+        std::fs::create_dir_all(root.join("sys/block/loop0")).unwrap();
+        let write_stats = |nvme_read: u64, nvme_written: u64| {
+            let stats = format!(
+                "   7       0 loop0 10 0 80 0 0 0 0 0 0 0 0 0 0 0 0 0 0\n\
+                 259       0 nvme0n1 100 0 {nvme_read} 20 50 0 {nvme_written} 30 0 40 50 0 0 0 0 0 0\n\
+                 259       1 nvme0n1p1 100 0 {nvme_read} 20 50 0 {nvme_written} 30 0 40 50 0 0 0 0 0 0\n"
+            );
+            root.write([("proc/diskstats", stats)]);
+        };
+        let mut throughput = Throughput::new(root.join("proc"), root.join("sys"));
+
+        write_stats(1000, 2000);
+        throughput.refresh();
+        let devices = throughput.devices().unwrap();
+        assert_eq!(devices.len(), 1);
+        assert_eq!(devices[0].name, "nvme0n1");
+        assert_eq!(devices[0].total_read, 512_000);
+        assert_eq!(devices[0].total_written, 1_024_000);
+        assert_eq!((devices[0].read, devices[0].write), (0.0, 0.0));
+
+        let earlier = Instant::now();
+        throughput.last_sample = Some(earlier);
+        throughput.sample(
+            parse("259 0 nvme0n1 100 0 3000 20 50 0 2000 30 0 40 50"),
+            earlier + Duration::from_secs(2),
+        );
+        let devices = throughput.devices().unwrap();
+        assert_eq!((devices[0].read, devices[0].write), (512_000.0, 0.0));
+
+        throughput.select = vec!["loop*".to_owned(), "*p1".to_owned()];
+        throughput.refresh();
+        let names = throughput
+            .devices()
+            .unwrap()
+            .iter()
+            .map(|d| d.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["loop0", "nvme0n1p1"]);
+
+        std::fs::remove_file(root.join("proc/diskstats")).unwrap();
+        throughput.refresh();
+        assert!(throughput.devices().is_err());
+    }
+}
@@ -0,0 +1,90 @@
+//! The recent values of a metric, for drawing graphs
+use std::{collections::VecDeque, fmt::Write};
+
+/// The size of the viewbox [`History::path_commands`] draws in
+pub const VIEWBOX_SIZE: f32 = 100.0;
+
+/// A ring buffer of the last `length` samples, oldest first.  Keeps nothing until a length is
+/// set.
+#[derive(Debug, Clone, Default)]
+pub struct History {
+    values: VecDeque<f32>,
+    length: usize,
+}
+
+impl History {
+    pub fn push(&mut self, value: f32) {
+        if self.length == 0 {
+            return;
+        }
+        if self.values.len() == self.length {
+            self.values.pop_front();
+        }
+        self.values.push_back(value);
+    }
+    /// Keep up to `length` samples from now on, dropping the oldest ones if there are more
+    pub fn set_length(&mut self, length: usize) {
+        let excess = self.values.len().saturating_sub(length);
+        self.values.drain(..excess);
+        self.length = length;
+    }
+    pub fn values(&self) -> impl Iterator<Item = f32> + '_ {
+        self.values.iter().copied()
+    }
+    /// The largest sample, or 0 if there are none
+    pub fn max(&self) -> f32 {
+        self.values().fold(0.0, f32::max)
+    }
+    /// Commands for a slint `Path` with a viewbox of [`VIEWBOX_SIZE`] squared, drawing the
+    /// samples as a line that reaches the top at `max`.  The newest sample is at the right edge,
+    /// so a history that isn't full yet grows from the right.
+    #[allow(clippy::cast_precision_loss)]
+    pub fn path_commands(&self, max: f32) -> String {
+        let step = VIEWBOX_SIZE / self.length.saturating_sub(1).max(1) as f32;
+        let start = self.length - self.values.len();
+        let mut commands = String::new();
+        for (i, value) in self.values().enumerate() {
+            let x = (start + i) as f32 * step;
+            let y = VIEWBOX_SIZE - (value / max.max(f32::EPSILON)).clamp(0.0, 1.0) * VIEWBOX_SIZE;
+            let command = if i == 0 { 'M' } else { 'L' };
+            let _ = write!(commands, "{command} {x:.1} {y:.1} ");
+        }
+        commands.pop();
+        commands
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ring_buffer() {
+        let mut history = History::default();
+        history.set_length(3);
+        for value in [1.0, 2.0, 3.0, 4.0] {
+            history.push(value);
+        }
+        assert_eq!(history.values().collect::<Vec<_>>(), [2.0, 3.0, 4.0]);
+        assert!((history.max() - 4.0).abs() < f32::EPSILON);
+
+        history.set_length(2);
+        assert_eq!(history.values().collect::<Vec<_>>(), [3.0, 4.0]);
+        history.push(5.0);
+        assert_eq!(history.values().collect::<Vec<_>>(), [4.0, 5.0]);
+    }
+
+    #[test]
+    fn path() {
+        let mut history = History::default();
+        history.set_length(5);
+        assert_eq!(history.path_commands(100.0), "");
+        history.push(50.0);
+        history.push(100.0);
+        history.push(0.0);
+        assert_eq!(
+            history.path_commands(100.0),
+            "M 50.0 50.0 L 75.0 0.0 L 100.0 100.0"
+        );
+    }
+}
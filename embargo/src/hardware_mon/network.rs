@@ -0,0 +1,77 @@
+//! Per-interface details sysinfo doesn't provide
+use std::{
+    collections::HashMap,
+    ffi::CStr,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+    path::Path,
+};
+
+/// The latest sample of a network interface
+#[derive(Debug, Clone, PartialEq)]
+pub struct Interface {
+    pub name: String,
+    /// bytes per second sent since the previous sample
+    pub up: f64,
+    /// bytes per second received since the previous sample
+    pub down: f64,
+    pub total_up: u64,
+    pub total_down: u64,
+    /// the operational state from sysfs, e.g. `up`, `down` or `dormant`
+    pub state: String,
+    pub addresses: Vec<IpAddr>,
+}
+
+/// The operational state of the interface called `name`.  `sysfs` is usually `/sys`.
+pub fn operational_state(sysfs: &Path, name: &str) -> String {
+    std::fs::read_to_string(sysfs.join("class/net").join(name).join("operstate"))
+        .map_or_else(|_| "unknown".to_owned(), |state| state.trim().to_owned())
+}
+
+/// The IP addresses of every interface, by interface name
+pub fn addresses() -> std::io::Result<HashMap<String, Vec<IpAddr>>> {
+    let mut list = std::ptr::null_mut();
+    // SAFETY: `list` is a valid place for the result
+    if unsafe { libc::getifaddrs(&raw mut list) } != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    let mut addresses = HashMap::<String, Vec<IpAddr>>::new();
+    let mut entry = list;
+    while !entry.is_null() {
+        // SAFETY: getifaddrs returned a valid list, which lives until freeifaddrs
+        let ifaddrs = unsafe { &*entry };
+        entry = ifaddrs.ifa_next;
+        if ifaddrs.ifa_addr.is_null() {
+            continue;
+        }
+        // SAFETY: the family says which kind of sockaddr `ifa_addr` points to
+        let address = unsafe {
+            match i32::from((*ifaddrs.ifa_addr).sa_family) {
+                libc::AF_INET => {
+                    let address = ifaddrs
+                        .ifa_addr
+                        .cast::<libc::sockaddr_in>()
+                        .read_unaligned();
+                    IpAddr::V4(Ipv4Addr::from(u32::from_be(address.sin_addr.s_addr)))
+                }
+                libc::AF_INET6 => {
+                    let address = ifaddrs
+                        .ifa_addr
+                        .cast::<libc::sockaddr_in6>()
+                        .read_unaligned();
+                    IpAddr::V6(Ipv6Addr::from(address.sin6_addr.s6_addr))
+                }
+                // e.g. the link layer address
+                _ => continue,
+            }
+        };
+        // SAFETY: the name is a valid C string owned by the list
+        let name = unsafe { CStr::from_ptr(ifaddrs.ifa_name) };
+        addresses
+            .entry(name.to_string_lossy().into_owned())
+            .or_default()
+            .push(address);
+    }
+    // SAFETY: `list` came from getifaddrs and nothing borrows from it anymore
+    unsafe { libc::freeifaddrs(list) };
+    Ok(addresses)
+}
@@ -0,0 +1,284 @@
+//! Batteries and AC adapters from the power supply class in sysfs
+use std::{path::Path, time::Duration};
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PowerSupplies {
+    /// sorted by name, e.g. `BAT0` before `BAT1`
+    pub batteries: Vec<Battery>,
+    /// whether any AC adapter is plugged in
+    pub ac_online: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Battery {
+    pub name: String,
+    /// charge in percent
+    pub capacity: f64,
+    /// e.g. `Charging`, `Discharging`, `Full` or `Not charging`
+    pub status: String,
+    /// stored energy in Wh
+    pub energy_now: Option<f64>,
+    /// energy when full in Wh
+    pub energy_full: Option<f64>,
+    /// power flowing in or out in W
+    pub power: Option<f64>,
+    pub time_to_empty: Option<Duration>,
+    pub time_to_full: Option<Duration>,
+}
+
+impl PowerSupplies {
+    /// The charge of all batteries together in percent, weighted by their size when known
+    pub fn capacity(&self) -> Option<f64> {
+        let energy =
+            |f: fn(&Battery) -> Option<f64>| self.batteries.iter().map(f).sum::<Option<f64>>();
+        match (energy(|b| b.energy_now), energy(|b| b.energy_full)) {
+            (Some(now), Some(full)) if full > 0.0 => Some(now / full * 100.0),
+            _ if self.batteries.is_empty() => None,
+            #[allow(clippy::cast_precision_loss)]
+            _ => Some(
+                self.batteries.iter().map(|b| b.capacity).sum::<f64>()
+                    / self.batteries.len() as f64,
+            ),
+        }
+    }
+    /// Whether any battery is discharging
+    pub fn discharging(&self) -> bool {
+        self.batteries.iter().any(|b| b.status == "Discharging")
+    }
+    /// `Charging` or `Discharging` if any battery is, or else the status of the first one
+    pub fn status(&self) -> &str {
+        ["Charging", "Discharging"]
+            .into_iter()
+            .find(|&status| self.batteries.iter().any(|b| b.status == status))
+            .or_else(|| Some(self.batteries.first()?.status.as_str()))
+            .unwrap_or_default()
+    }
+    /// How long until all batteries are empty when discharging, or full when charging, at the
+    /// current draw
+    pub fn time_remaining(&self) -> Option<Duration> {
+        let draw = self.batteries.iter().filter_map(|b| b.power).sum::<f64>();
+        let now = self
+            .batteries
+            .iter()
+            .map(|b| b.energy_now)
+            .sum::<Option<f64>>()?;
+        let energy = match self.status() {
+            "Discharging" => now,
+            "Charging" => {
+                self.batteries
+                    .iter()
+                    .map(|b| b.energy_full)
+                    .sum::<Option<f64>>()?
+                    - now
+            }
+            _ => return None,
+        };
+        (draw > 0.0).then(|| Duration::from_secs_f64(energy.max(0.0) / draw * 3600.0))
+    }
+}
+
+/// Every power supply under `sysfs`, usually `/sys`.  Batteries of devices like mice are left
+/// out.
+pub fn read(sysfs: &Path) -> std::io::Result<PowerSupplies> {
+    let entries = match std::fs::read_dir(sysfs.join("class/power_supply")) {
+        Ok(entries) => entries,
+        // nothing to report on a desktop without the driver
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(PowerSupplies::default()),
+        Err(e) => return Err(e),
+    };
+    let mut supplies = entries
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<std::io::Result<Vec<_>>>()?;
+    supplies.sort();
+    let mut power = PowerSupplies::default();
+    for supply in supplies {
+        let read_string = |file: &str| {
+            std::fs::read_to_string(supply.join(file))
+                .ok()
+                .map(|s| s.trim().to_owned())
+        };
+        let read_number = |file: &str| read_string(file)?.parse::<f64>().ok();
+        if read_string("scope").as_deref() == Some("Device") {
+            continue;
+        }
+        match read_string("type").as_deref() {
+            Some("Mains" | "USB") => {
+                power.ac_online |= read_number("online").is_some_and(|online| online != 0.0);
+            }
+            Some("Battery") => {
+                // sysfs uses micro units: µWh, µAh, µW, µA and µV
+                let voltage = read_number("voltage_now").map(|v| v / 1e6);
+                let design_voltage = read_number("voltage_min_design")
+                    .map(|v| v / 1e6)
+                    .or(voltage);
+                let energy = |name: &str| {
+                    read_number(&format!("energy_{name}"))
+                        .map(|e| e / 1e6)
+                        .or_else(|| {
+                            // batteries that only report charge
+                            Some(read_number(&format!("charge_{name}"))? / 1e6 * design_voltage?)
+                        })
+                };
+                let (energy_now, energy_full) = (energy("now"), energy("full"));
+                let power_draw = read_number("power_now")
+                    .map(|p| p / 1e6)
+                    .or_else(|| Some(read_number("current_now")? / 1e6 * voltage?))
+                    .map(f64::abs);
+                let status = read_string("status").unwrap_or_else(|| "Unknown".to_owned());
+                let capacity = read_number("capacity")
+                    .or_else(|| Some(energy_now? / energy_full? * 100.0))
+                    .unwrap_or_default();
+                // how long `energy` Wh last at the current draw
+                let hours = |energy: Option<f64>| match (energy, power_draw) {
+                    (Some(energy), Some(draw)) if draw > 0.0 => {
+                        Some(Duration::from_secs_f64(energy.max(0.0) / draw * 3600.0))
+                    }
+                    _ => None,
+                };
+                power.batteries.push(Battery {
+                    name: supply
+                        .file_name()
+                        .map(|name| name.to_string_lossy().into_owned())
+                        .unwrap_or_default(),
+                    time_to_empty: if status == "Discharging" {
+                        hours(energy_now)
+                    } else {
+                        None
+                    },
+                    time_to_full: if status == "Charging" {
+                        hours(energy_full.zip(energy_now).map(|(full, now)| full - now))
+                    } else {
+                        None
+                    },
+                    capacity,
+                    status,
+                    energy_now,
+                    energy_full,
+                    power: power_draw,
+                });
+            }
+            _ => {}
+        }
+    }
+    Ok(power)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Normal,
+    Low,
+    Critical,
+}
+
+/// Tells when the battery crosses the low and critical thresholds while discharging, once per
+/// crossing
+#[derive(Debug, Default)]
+pub struct Alerts {
+    level: Option<Level>,
+}
+
+impl Alerts {
+    /// The level the battery entered since the last call, if it got worse
+    pub fn check(&mut self, level: Level) -> Option<Level> {
+        let previous = self.level.replace(level);
+        (level > Level::Normal && previous.is_none_or(|previous| level > previous)).then_some(level)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::temp_dir::TempDir;
+
+    fn sysfs(test: &str, files: &[(&str, &str)]) -> TempDir {
+        TempDir::with_files(
+            test,
+            files.iter().map(|(path, contents)| {
+                (
+                    Path::new("class/power_supply").join(path),
+                    format!("{contents}\n"),
+                )
+            }),
+        )
+    }
+
+    #[test]
+    fn two_batteries() {
+        let root = sysfs(
+            "power-two",
+            &[
+                ("AC/type", "Mains"),
+                ("AC/online", "0"),
+                ("BAT0/type", "Battery"),
+                ("BAT0/status", "Discharging"),
+                ("BAT0/capacity", "50"),
+                ("BAT0/energy_now", "20000000"),
+                ("BAT0/energy_full", "40000000"),
+                ("BAT0/power_now", "10000000"),
+                // reports charge and current instead of energy and power
+                ("BAT1/type", "Battery"),
+                ("BAT1/status", "Discharging"),
+                ("BAT1/charge_now", "1000000"),
+                ("BAT1/charge_full", "2000000"),
+                ("BAT1/voltage_now", "10000000"),
+                ("BAT1/current_now", "500000"),
+                ("hidpp_battery_0/type", "Battery"),
+                ("hidpp_battery_0/scope", "Device"),
+                ("hidpp_battery_0/capacity", "5"),
+            ],
+        );
+        let power = read(&root).unwrap();
+        assert!(!power.ac_online);
+        assert_eq!(power.batteries.len(), 2);
+        let [bat0, bat1] = &power.batteries[..] else {
+            unreachable!()
+        };
+        assert_eq!(bat0.energy_now, Some(20.0));
+        assert_eq!(bat0.power, Some(10.0));
+        assert_eq!(bat0.time_to_empty, Some(Duration::from_hours(2)));
+        assert_eq!(bat0.time_to_full, None);
+        assert_eq!(bat1.energy_now, Some(10.0));
+        assert_eq!(bat1.energy_full, Some(20.0));
+        assert!((bat1.capacity - 50.0).abs() < f64::EPSILON);
+        assert_eq!(bat1.power, Some(5.0));
+        assert_eq!(power.capacity(), Some(50.0));
+        assert!(power.discharging());
+        assert_eq!(power.status(), "Discharging");
+        assert_eq!(power.time_remaining(), Some(Duration::from_hours(2)));
+    }
+
+    #[test]
+    fn charging() {
+        let root = sysfs(
+            "power-charging",
+            &[
+                ("ADP1/type", "Mains"),
+                ("ADP1/online", "1"),
+                ("BAT0/type", "Battery"),
+                ("BAT0/status", "Charging"),
+                ("BAT0/capacity", "75"),
+                ("BAT0/energy_now", "30000000"),
+                ("BAT0/energy_full", "40000000"),
+                ("BAT0/power_now", "20000000"),
+            ],
+        );
+        let power = read(&root).unwrap();
+        assert!(power.ac_online);
+        assert_eq!(
+            power.batteries[0].time_to_full,
+            Some(Duration::from_mins(30))
+        );
+        assert_eq!(power.capacity(), Some(75.0));
+    }
+
+    #[test]
+    fn alerts() {
+        let mut alerts = Alerts::default();
+        assert_eq!(alerts.check(Level::Normal), None);
+        assert_eq!(alerts.check(Level::Low), Some(Level::Low));
+        assert_eq!(alerts.check(Level::Low), None);
+        assert_eq!(alerts.check(Level::Critical), Some(Level::Critical));
+        assert_eq!(alerts.check(Level::Normal), None);
+        assert_eq!(alerts.check(Level::Low), Some(Level::Low));
+    }
+}
@@ -0,0 +1,122 @@
+//! The processes using the most cpu and memory
+use sysinfo::{PidExt, ProcessExt, System, SystemExt, UserExt};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Process {
+    pub pid: u32,
+    pub name: String,
+    /// the name of the user running it, or empty if unknown
+    pub user: String,
+    /// usage in percent of a single core, so it goes above 100 for processes with many threads
+    pub cpu: f32,
+    /// resident memory in bytes
+    pub memory: u64,
+}
+
+/// The processes with the highest usage, highest first
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Top {
+    pub by_cpu: Vec<Process>,
+    pub by_memory: Vec<Process>,
+}
+
+/// Every process as of the last process refresh of `system`
+pub fn list(system: &System) -> Vec<Process> {
+    system
+        .processes()
+        .values()
+        .map(|process| Process {
+            pid: process.pid().as_u32(),
+            name: process.name().to_owned(),
+            user: process
+                .user_id()
+                .and_then(|uid| system.get_user_by_id(uid))
+                .map(|user| user.name().to_owned())
+                .unwrap_or_default(),
+            cpu: process.cpu_usage(),
+            memory: process.memory(),
+        })
+        .collect()
+}
+
+/// The `count` processes using the most cpu and the most memory.  Ties are broken by pid, so
+/// the order doesn't jump around between refreshes.
+pub fn top(mut processes: Vec<Process>, count: usize) -> Top {
+    processes.sort_by(|a, b| b.cpu.total_cmp(&a.cpu).then(a.pid.cmp(&b.pid)));
+    let by_cpu = processes.iter().take(count).cloned().collect();
+    processes.sort_by(|a, b| b.memory.cmp(&a.memory).then(a.pid.cmp(&b.pid)));
+    processes.truncate(count);
+    Top {
+        by_cpu,
+        by_memory: processes,
+    }
+}
+
+/// Send `signal` to `pid`.  The signal is a name like `TERM` or `SIGKILL`, or a number.
+pub fn send_signal(pid: u32, signal: &str) -> anyhow::Result<()> {
+    let number =
+        signal_number(signal).ok_or_else(|| anyhow::anyhow!("unknown signal '{signal}'"))?;
+    let pid = libc::pid_t::try_from(pid)?;
+    // 0 would signal our own process group
+    anyhow::ensure!(pid > 0, "invalid pid {pid}");
+    // SAFETY: kill has no memory safety requirements
+    if unsafe { libc::kill(pid, number) } != 0 {
+        return Err(std::io::Error::last_os_error().into());
+    }
+    Ok(())
+}
+
+fn signal_number(signal: &str) -> Option<libc::c_int> {
+    if let Ok(number) = signal.parse() {
+        return Some(number);
+    }
+    let name = signal.to_ascii_uppercase();
+    Some(match name.strip_prefix("SIG").unwrap_or(&name) {
+        "HUP" => libc::SIGHUP,
+        "INT" => libc::SIGINT,
+        "QUIT" => libc::SIGQUIT,
+        "KILL" => libc::SIGKILL,
+        "USR1" => libc::SIGUSR1,
+        "USR2" => libc::SIGUSR2,
+        "TERM" => libc::SIGTERM,
+        "CONT" => libc::SIGCONT,
+        "STOP" => libc::SIGSTOP,
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn top_processes() {
+        let process = |pid, cpu, memory| Process {
+            pid,
+            name: format!("process{pid}"),
+            user: "user".to_owned(),
+            cpu,
+            memory,
+        };
+        let processes = vec![
+            process(1, 0.0, 500),
+            process(2, 150.0, 100),
+            process(3, 20.0, 900),
+            process(4, 20.0, 10),
+        ];
+        let top = top(processes, 2);
+        let pids = |list: &[Process]| list.iter().map(|p| p.pid).collect::<Vec<_>>();
+        assert_eq!(pids(&top.by_cpu), [2, 3]);
+        assert_eq!(pids(&top.by_memory), [3, 1]);
+    }
+
+    #[test]
+    fn signals() {
+        assert_eq!(signal_number("TERM"), Some(libc::SIGTERM));
+        assert_eq!(signal_number("sigkill"), Some(libc::SIGKILL));
+        assert_eq!(signal_number("10"), Some(10));
+        assert_eq!(signal_number("BOGUS"), None);
+        assert!(send_signal(0, "TERM").is_err());
+        assert!(send_signal(1, "BOGUS").is_err());
+    }
+}
@@ -0,0 +1,112 @@
+//! Finds the interface carrying the default route, from the kernel's routing tables in procfs
+use std::{io::ErrorKind, path::Path};
+
+const RTF_UP: u32 = 0x1;
+const RTF_REJECT: u32 = 0x200;
+
+/// The interface of the default route with the lowest metric.  IPv4 routes are preferred, IPv6
+/// ones are only used on IPv6-only networks.  `procfs` is usually `/proc`.
+pub fn default_interface(procfs: &Path) -> std::io::Result<Option<String>> {
+    let ipv4 = std::fs::read_to_string(procfs.join("net/route"))?;
+    if let Some(interface) = best(ipv4.lines().skip(1).filter_map(ipv4_default)) {
+        return Ok(Some(interface));
+    }
+    let ipv6 = match std::fs::read_to_string(procfs.join("net/ipv6_route")) {
+        Ok(ipv6) => ipv6,
+        // IPv6 is disabled
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    Ok(best(ipv6.lines().filter_map(ipv6_default)))
+}
+
+fn best<'a>(routes: impl Iterator<Item = (&'a str, u32)>) -> Option<String> {
+    routes
+        .min_by_key(|&(_, metric)| metric)
+        .map(|(interface, _)| interface.to_owned())
+}
+
+/// `(interface, metric)` of a line of `/proc/net/route` that is a default route
+fn ipv4_default(line: &str) -> Option<(&str, u32)> {
+    let fields = line.split_whitespace().collect::<Vec<_>>();
+    let &[interface, destination, _gateway, flags, _refcnt, _use, metric, mask, ..] = &fields[..]
+    else {
+        return None;
+    };
+    let flags = u32::from_str_radix(flags, 16).ok()?;
+    let usable = flags & RTF_UP != 0 && flags & RTF_REJECT == 0;
+    (usable && is_zero(destination) && is_zero(mask)).then_some((interface, metric.parse().ok()?))
+}
+
+/// `(interface, metric)` of a line of `/proc/net/ipv6_route` that is a default route
+fn ipv6_default(line: &str) -> Option<(&str, u32)> {
+    let fields = line.split_whitespace().collect::<Vec<_>>();
+    let &[destination, prefix_length, _source, _source_prefix_length, _next_hop, metric, _refcnt, _use, flags, interface] =
+        &fields[..]
+    else {
+        return None;
+    };
+    let flags = u32::from_str_radix(flags, 16).ok()?;
+    let usable = flags & RTF_UP != 0 && flags & RTF_REJECT == 0 && interface != "lo";
+    (usable && is_zero(destination) && is_zero(prefix_length))
+        .then_some((interface, u32::from_str_radix(metric, 16).ok()?))
+}
+
+fn is_zero(hex: &str) -> bool {
+    hex.bytes().all(|b| b == b'0')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::temp_dir::TempDir;
+
+    const ROUTE_HEADER: &str =
+        "Iface\tDestination\tGateway \tFlags\tRefCnt\tUse\tMetric\tMask\t\tMTU\tWindow\tIRTT\n";
+    /// the reject route the kernel always has on `lo`
+    const IPV6_LO: &str = "00000000000000000000000000000000 00 00000000000000000000000000000000 00 00000000000000000000000000000000 ffffffff 00000001 00000000 00200200       lo\n";
+
+    fn procfs(test: &str, route: &str, ipv6_route: Option<&str>) -> TempDir {
+        let root = TempDir::with_files(test, [("net/route", format!("{ROUTE_HEADER}{route}"))]);
+        if let Some(ipv6_route) = ipv6_route {
+            root.write([("net/ipv6_route", ipv6_route)]);
+        }
+        root
+    }
+
+    #[test]
+    fn lowest_metric() {
+        let root = procfs(
+            "route-metric",
+            "enp6s0\t0001A8C0\t00000000\t0001\t0\t0\t100\t00FFFFFF\t0\t0\t0\n\
+             enp6s0\t00000000\t0101A8C0\t0003\t0\t0\t100\t00000000\t0\t0\t0\n\
+             wlan0\t00000000\t0101A8C0\t0003\t0\t0\t600\t00000000\t0\t0\t0\n",
+            Some(IPV6_LO),
+        );
+        assert_eq!(default_interface(&root).unwrap().as_deref(), Some("enp6s0"));
+    }
+
+    #[test]
+    fn ipv6_only() {
+        let root = procfs(
+            "route-ipv6",
+            "",
+            Some(&format!(
+                "fd000000000000000000000000000000 40 00000000000000000000000000000000 00 00000000000000000000000000000000 00000100 00000001 00000000 00000001     wlan0\n\
+                 00000000000000000000000000000000 00 00000000000000000000000000000000 00 fd000000000000000000000000000001 00000258 00000001 00000000 00000003     wlan0\n\
+                 {IPV6_LO}"
+            )),
+        );
+        assert_eq!(default_interface(&root).unwrap().as_deref(), Some("wlan0"));
+    }
+
+    #[test]
+    fn no_default_route() {
+        let root = procfs(
+            "route-none",
+            "enp6s0\t0001A8C0\t00000000\t0001\t0\t0\t100\t00FFFFFF\t0\t0\t0\n",
+            None,
+        );
+        assert_eq!(default_interface(&root).unwrap(), None);
+    }
+}
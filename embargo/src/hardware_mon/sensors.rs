@@ -0,0 +1,173 @@
+//! Temperature and fan sensors from the hwmon class in sysfs
+use std::path::Path;
+
+use crate::config::SensorSelector;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Kind {
+    Temperature,
+    Fan,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sensor {
+    /// the name of the driver, e.g. `k10temp` or `nvme`
+    pub chip: String,
+    /// e.g. `Tctl`, or the name of the input like `temp1` if the driver doesn't label it
+    pub label: String,
+    pub kind: Kind,
+    /// degrees Celsius or RPM
+    pub value: f64,
+    /// the temperature that counts as critical, if the driver knows it
+    pub critical_value: Option<f64>,
+    /// above the critical temperature, or the driver raised an alarm
+    pub critical: bool,
+}
+
+/// Every sensor under `sysfs`, usually `/sys`, that matches one of `select`, or every sensor if
+/// `select` is empty.  Inputs that can't be read, e.g. of a device that is asleep, are skipped.
+pub fn read(sysfs: &Path, select: &[SensorSelector]) -> std::io::Result<Vec<Sensor>> {
+    let entries = match std::fs::read_dir(sysfs.join("class/hwmon")) {
+        Ok(entries) => entries,
+        // no sensors at all, e.g. in a virtual machine
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    let mut chips = entries
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<std::io::Result<Vec<_>>>()?;
+    chips.sort();
+    let mut sensors = Vec::new();
+    for chip_dir in chips {
+        let Some(chip) = read_string(&chip_dir.join("name")) else {
+            continue;
+        };
+        let mut inputs = std::fs::read_dir(&chip_dir)?
+            .filter_map(Result::ok)
+            .filter_map(|entry| input(&entry.file_name().to_string_lossy()))
+            .collect::<Vec<_>>();
+        inputs.sort();
+        for (kind, _, prefix) in inputs {
+            let label =
+                read_string(&chip_dir.join(format!("{prefix}_label"))).unwrap_or(prefix.clone());
+            let selected = select.is_empty()
+                || select.iter().any(|s| {
+                    layer_platform::glob_match(&s.chip, &chip)
+                        && layer_platform::glob_match(&s.label, &label)
+                });
+            if !selected {
+                continue;
+            }
+            let read_value = |file: &str| read_number(&chip_dir.join(format!("{prefix}_{file}")));
+            let Some(value) = read_value("input") else {
+                continue;
+            };
+            let alarm = read_value("alarm").is_some_and(|alarm| alarm != 0.0);
+            sensors.push(match kind {
+                Kind::Temperature => {
+                    // in millidegrees
+                    let value = value / 1000.0;
+                    let critical_value = read_value("crit").map(|crit| crit / 1000.0);
+                    let crit_alarm = read_value("crit_alarm").is_some_and(|alarm| alarm != 0.0);
+                    Sensor {
+                        critical: alarm
+                            || crit_alarm
+                            || critical_value.is_some_and(|crit| value >= crit),
+                        chip: chip.clone(),
+                        label,
+                        kind,
+                        value,
+                        critical_value,
+                    }
+                }
+                Kind::Fan => Sensor {
+                    chip: chip.clone(),
+                    label,
+                    kind,
+                    value,
+                    critical_value: None,
+                    critical: alarm,
+                },
+            });
+        }
+    }
+    Ok(sensors)
+}
+
+/// `(kind, index, prefix)` of an input file name like `temp1_input`, which sort in order
+fn input(file_name: &str) -> Option<(Kind, u32, String)> {
+    let prefix = file_name.strip_suffix("_input")?;
+    let (kind, index) = if let Some(index) = prefix.strip_prefix("temp") {
+        (Kind::Temperature, index)
+    } else {
+        (Kind::Fan, prefix.strip_prefix("fan")?)
+    };
+    Some((kind, index.parse().ok()?, prefix.to_owned()))
+}
+
+fn read_string(path: &Path) -> Option<String> {
+    std::fs::read_to_string(path)
+        .ok()
+        .map(|s| s.trim().to_owned())
+}
+
+fn read_number(path: &Path) -> Option<f64> {
+    read_string(path)?.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::temp_dir::TempDir;
+
+    /// A sysfs with a cpu that is too hot and a fan that stopped
+    fn sysfs() -> TempDir {
+        let files = [
+            ("hwmon0/name", "k10temp"),
+            ("hwmon0/temp1_input", "95500"),
+            ("hwmon0/temp1_label", "Tctl"),
+            ("hwmon0/temp1_crit", "95000"),
+            ("hwmon0/temp3_input", "40000"),
+            ("hwmon1/name", "nct6775"),
+            ("hwmon1/fan2_input", "0"),
+            ("hwmon1/fan2_alarm", "1"),
+            ("hwmon1/fan1_input", "1200"),
+            ("hwmon1/fan1_label", "CPU fan"),
+        ];
+        TempDir::with_files(
+            "sensors",
+            files.map(|(path, contents)| (format!("class/hwmon/{path}"), format!("{contents}\n"))),
+        )
+    }
+
+    #[test]
+    fn hwmon() {
+        let root = sysfs();
+        let sensors = read(&root, &[]).unwrap();
+        let summary = sensors
+            .iter()
+            .map(|s| (s.chip.as_str(), s.label.as_str(), s.value, s.critical))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            [
+                ("k10temp", "Tctl", 95.5, true),
+                ("k10temp", "temp3", 40.0, false),
+                ("nct6775", "CPU fan", 1200.0, false),
+                ("nct6775", "fan2", 0.0, true),
+            ]
+        );
+        assert_eq!(sensors[0].critical_value, Some(95.0));
+        assert_eq!(sensors[2].kind, Kind::Fan);
+
+        let select = [SensorSelector {
+            chip: "k10*".to_owned(),
+            label: "Tctl".to_owned(),
+        }];
+        let sensors = read(&root, &select).unwrap();
+        assert_eq!(sensors.len(), 1);
+        assert_eq!(sensors[0].label, "Tctl");
+        // no hwmon class at all
+        assert_eq!(read(&root.join("empty"), &[]).unwrap(), []);
+    }
+}
@@ -0,0 +1,82 @@
+//! Kernel statistics from procfs that sysinfo doesn't provide
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Stats {
+    /// bytes of memory used by buffers, the page cache and reclaimable slabs, like the
+    /// `buff/cache` column of `free`
+    pub cached_memory: u64,
+    /// how many processes there are, not counting threads
+    pub processes: usize,
+}
+
+/// Read the stats from `procfs`, usually `/proc`
+pub fn read(procfs: &Path) -> std::io::Result<Stats> {
+    let meminfo = std::fs::read_to_string(procfs.join("meminfo"))?;
+    let processes = std::fs::read_dir(procfs)?
+        .filter_map(Result::ok)
+        .filter(|entry| {
+            entry
+                .file_name()
+                .to_str()
+                .is_some_and(|name| name.bytes().all(|b| b.is_ascii_digit()))
+        })
+        .count();
+    Ok(Stats {
+        cached_memory: cached_memory(&meminfo),
+        processes,
+    })
+}
+
+/// The cached memory in bytes from the contents of `/proc/meminfo`
+fn cached_memory(meminfo: &str) -> u64 {
+    meminfo
+        .lines()
+        .filter_map(|line| {
+            let (key, value) = line.split_once(':')?;
+            if !matches!(key, "Buffers" | "Cached" | "SReclaimable") {
+                return None;
+            }
+            // always in kB, which means KiB
+            let kib = value
+                .trim()
+                .strip_suffix("kB")?
+                .trim()
+                .parse::<u64>()
+                .ok()?;
+            Some(kib * 1024)
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::temp_dir::TempDir;
+
+    #[test]
+    fn procfs() {
+        let root = TempDir::with_files(
+            "stats",
+            [(
+                "meminfo",
+                "MemTotal:       32768000 kB\n\
+                 MemFree:         1000000 kB\n\
+                 Buffers:             100 kB\n\
+                 Cached:             2000 kB\n\
+                 SwapCached:        50000 kB\n\
+                 SReclaimable:        300 kB\n",
+            )],
+        );
+        for dir in ["1", "42", "1337", "self", "sys"] {
+            std::fs::create_dir_all(root.join(dir)).unwrap();
+        }
+        assert_eq!(
+            read(&root).unwrap(),
+            Stats {
+                cached_memory: 2400 * 1024,
+                processes: 3,
+            }
+        );
+    }
+}
@@ -0,0 +1,140 @@
+use std::{
+    fs::File,
+    io::{ErrorKind, Read, Seek, Write},
+    os::fd::AsRawFd,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+/// How long a replaced instance gets to exit
+const REPLACE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// `$XDG_RUNTIME_DIR`, where the files of the instances are kept
+pub fn runtime_dir() -> anyhow::Result<PathBuf> {
+    let Some(runtime_dir) = std::env::var_os("XDG_RUNTIME_DIR") else {
+        anyhow::bail!("XDG_RUNTIME_DIR is not set");
+    };
+    Ok(runtime_dir.into())
+}
+
+/// A file in `runtime_dir` belonging to the instance called `name`, e.g. `embargo.sock` or
+/// `embargo-top.sock`
+pub fn runtime_path(runtime_dir: &Path, name: Option<&str>, extension: &str) -> PathBuf {
+    let file_name = match name {
+        Some(name) => format!("{}-{name}.{extension}", clap::crate_name!()),
+        None => format!("{}.{extension}", clap::crate_name!()),
+    };
+    runtime_dir.join(file_name)
+}
+
+/// Held for as long as the instance runs, so a second one with the same name can't start.  The
+/// lock file contains the pid of the holder.
+pub struct Lock {
+    _file: File,
+}
+
+impl Lock {
+    /// Take the lock in `runtime_dir` for the instance called `name`.  If another instance holds
+    /// it, it is asked to exit when `replace` is set and refused otherwise.
+    pub fn acquire(runtime_dir: &Path, name: Option<&str>, replace: bool) -> anyhow::Result<Self> {
+        let path = runtime_path(runtime_dir, name, "lock");
+        let mut file = File::options()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)?;
+        if !try_lock(&file)? {
+            let mut pid = String::new();
+            file.read_to_string(&mut pid)?;
+            let pid = pid.trim().parse::<libc::pid_t>().ok();
+            let instance = name.map_or_else(|| "an instance".to_owned(), |n| format!("'{n}'"));
+            let (Some(pid), true) = (pid, replace) else {
+                anyhow::bail!(
+                    "{instance} is already running (pid {}).  Use --replace to replace it",
+                    pid.map_or_else(|| "unknown".to_owned(), |p| p.to_string())
+                );
+            };
+            tracing::info!("asking {instance} (pid {pid}) to exit");
+            // SAFETY: kill has no memory safety requirements
+            if unsafe { libc::kill(pid, libc::SIGTERM) } != 0 {
+                return Err(std::io::Error::last_os_error().into());
+            }
+            let start = Instant::now();
+            while !try_lock(&file)? {
+                if start.elapsed() > REPLACE_TIMEOUT {
+                    anyhow::bail!("{instance} (pid {pid}) did not exit");
+                }
+                std::thread::sleep(Duration::from_millis(50));
+            }
+        }
+        file.set_len(0)?;
+        file.rewind()?;
+        writeln!(file, "{}", std::process::id())?;
+        Ok(Self { _file: file })
+    }
+}
+
+/// Take an exclusive lock on `file` without blocking.  Returns false if it is held elsewhere.
+fn try_lock(file: &File) -> anyhow::Result<bool> {
+    // SAFETY: the fd stays open for the duration of the call
+    if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) } == 0 {
+        return Ok(true);
+    }
+    let error = std::io::Error::last_os_error();
+    match error.kind() {
+        ErrorKind::WouldBlock => Ok(false),
+        _ => Err(error.into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::temp_dir::TempDir;
+
+    #[test]
+    fn file_names() {
+        let dir = Path::new("/run/user/1000");
+        assert_eq!(
+            runtime_path(dir, None, "sock"),
+            Path::new("/run/user/1000/embargo.sock")
+        );
+        assert_eq!(
+            runtime_path(dir, Some("top"), "lock"),
+            Path::new("/run/user/1000/embargo-top.lock")
+        );
+    }
+
+    #[test]
+    fn second_instance_refused() {
+        let dir = TempDir::new("lock");
+        let lock = Lock::acquire(&dir, Some("top"), false).unwrap();
+        // flock is per open file description, so this conflicts even in the same process
+        let error = Lock::acquire(&dir, Some("top"), false).err().unwrap();
+        assert_eq!(
+            error.to_string(),
+            format!(
+                "'top' is already running (pid {}).  Use --replace to replace it",
+                std::process::id()
+            )
+        );
+        // other names have their own lock
+        let _bottom = Lock::acquire(&dir, Some("bottom"), false).unwrap();
+        drop(lock);
+        Lock::acquire(&dir, Some("top"), false).unwrap();
+    }
+
+    #[test]
+    fn lock_file_holds_pid() {
+        let dir = TempDir::new("lock-pid");
+        let path = runtime_path(&dir, None, "lock");
+        // left by an instance that crashed, and longer than the new pid
+        std::fs::write(&path, "123456789\n").unwrap();
+        let _lock = Lock::acquire(&dir, None, false).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(path).unwrap(),
+            format!("{}\n", std::process::id())
+        );
+    }
+}
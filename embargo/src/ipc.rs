@@ -0,0 +1,365 @@
+use std::{
+    io::{BufRead, BufReader, Write},
+    os::unix::net::{UnixListener, UnixStream},
+    path::PathBuf,
+    sync::mpsc,
+};
+
+use slint::{Model, ModelRc, VecModel};
+use slint_interpreter::{Struct, Value};
+
+use crate::{cli, event_loop::Waker, instance};
+
+/// One line of JSON sent to a running instance, e.g.
+/// `{"command": "set_property", "name": "time", "value": "noon"}`
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum Request {
+    /// Set a property on the bar with index `bar`, or on every bar that has it
+    SetProperty {
+        name: String,
+        value: serde_json::Value,
+        bar: Option<usize>,
+    },
+    SetGlobal {
+        global: String,
+        name: String,
+        value: serde_json::Value,
+        bar: Option<usize>,
+    },
+    GetProperty {
+        name: String,
+        #[serde(default)]
+        bar: usize,
+    },
+    GetGlobal {
+        global: String,
+        name: String,
+        #[serde(default)]
+        bar: usize,
+    },
+    /// Run the script called `script`, or all of them, now
+    Refresh { script: Option<String> },
+    /// Read the config again and replace every bar
+    Reload,
+    /// Show the bar with index `bar`, or every bar, on `output` or on all outputs
+    Show {
+        output: Option<String>,
+        bar: Option<usize>,
+    },
+    Hide {
+        output: Option<String>,
+        bar: Option<usize>,
+    },
+    Toggle {
+        output: Option<String>,
+        bar: Option<usize>,
+    },
+    /// Turn auto-hiding of the bar with index `bar`, or every bar, on or off
+    AutoHide { enabled: bool, bar: Option<usize> },
+    /// Pop up the on-screen display
+    Osd { icon: String, value: f32 },
+}
+
+/// The answer to a [`Request`], sent back as one line of JSON
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct Response {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<serde_json::Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl From<anyhow::Result<()>> for Response {
+    fn from(result: anyhow::Result<()>) -> Self {
+        Self {
+            value: None,
+            error: result.err().map(|e| e.to_string()),
+        }
+    }
+}
+
+impl From<cli::Message> for Request {
+    fn from(message: cli::Message) -> Self {
+        use cli::Message;
+        let parse = |value: String| serde_json::from_str(&value).unwrap_or(value.into());
+        match message {
+            Message::Set {
+                name,
+                value,
+                global: Some(global),
+                bar,
+            } => Self::SetGlobal {
+                global,
+                name,
+                value: parse(value),
+                bar,
+            },
+            Message::Set {
+                name, value, bar, ..
+            } => Self::SetProperty {
+                name,
+                value: parse(value),
+                bar,
+            },
+            Message::Get {
+                name,
+                global: Some(global),
+                bar,
+            } => Self::GetGlobal { global, name, bar },
+            Message::Get { name, bar, .. } => Self::GetProperty { name, bar },
+            Message::Refresh { script } => Self::Refresh { script },
+            Message::Reload => Self::Reload,
+            Message::Show { output, bar } => Self::Show { output, bar },
+            Message::Hide { output, bar } => Self::Hide { output, bar },
+            Message::Toggle { output, bar } => Self::Toggle { output, bar },
+            Message::AutoHide { off, bar } => Self::AutoHide { enabled: !off, bar },
+            Message::Osd { icon, value } => Self::Osd { icon, value },
+        }
+    }
+}
+
+/// The control socket of the instance called `name`, `$XDG_RUNTIME_DIR/embargo[-name].sock`
+pub fn socket_path(name: Option<&str>) -> anyhow::Result<PathBuf> {
+    Ok(instance::runtime_path(
+        &instance::runtime_dir()?,
+        name,
+        "sock",
+    ))
+}
+
+/// Accepts connections on the control socket.  Every connection gets a thread that passes its
+/// requests on to the main loop and writes back the responses.
+pub struct Server {
+    /// removed again on exit.  `None` for sockets owned by someone else.
+    path: Option<PathBuf>,
+    requests: mpsc::Receiver<(Request, mpsc::Sender<Response>)>,
+}
+
+impl Server {
+    pub fn bind(name: Option<&str>, waker: Waker) -> anyhow::Result<Self> {
+        let path = socket_path(name)?;
+        if UnixStream::connect(&path).is_ok() {
+            anyhow::bail!(
+                "another instance is already listening on '{}'",
+                path.to_string_lossy()
+            );
+        }
+        // left behind by an instance that didn't exit cleanly
+        let _ = std::fs::remove_file(&path);
+        let listener = UnixListener::bind(&path)?;
+        tracing::debug!("listening on '{}'", path.to_string_lossy());
+        Ok(Self::listen(listener, Some(path), waker))
+    }
+    /// Accept connections on a socket that is already listening, like one passed by systemd
+    pub fn listen(listener: UnixListener, path: Option<PathBuf>, waker: Waker) -> Self {
+        let (sender, requests) = mpsc::channel();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let stream = match stream {
+                    Ok(stream) => stream,
+                    Err(e) => {
+                        tracing::warn!("failed to accept a connection: {e}");
+                        continue;
+                    }
+                };
+                let sender = sender.clone();
+                let waker = waker.clone();
+                std::thread::spawn(move || {
+                    if let Err(e) = serve(stream, &sender, &waker) {
+                        tracing::debug!("connection closed: {e}");
+                    }
+                });
+            }
+        });
+        Self { path, requests }
+    }
+    /// The requests that arrived since the last call, with where to send their response
+    pub fn requests(&self) -> impl Iterator<Item = (Request, mpsc::Sender<Response>)> + '_ {
+        self.requests.try_iter()
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        if let Some(path) = &self.path {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+fn serve(
+    stream: UnixStream,
+    requests: &mpsc::Sender<(Request, mpsc::Sender<Response>)>,
+    waker: &Waker,
+) -> anyhow::Result<()> {
+    let mut writer = stream.try_clone()?;
+    for line in BufReader::new(stream).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let response = match serde_json::from_str(&line) {
+            Ok(request) => {
+                let (sender, response) = mpsc::channel();
+                requests.send((request, sender))?;
+                waker.wake();
+                response.recv()?
+            }
+            Err(e) => Err(anyhow::anyhow!("invalid request: {e}")).into(),
+        };
+        serde_json::to_writer(&mut writer, &response)?;
+        writer.write_all(b"\n")?;
+    }
+    Ok(())
+}
+
+/// Send `request` to the running instance called `name` and wait for its response.  Errors
+/// reported by the instance are returned as errors.
+pub fn send(name: Option<&str>, request: &Request) -> anyhow::Result<Option<serde_json::Value>> {
+    let path = socket_path(name)?;
+    let mut stream = UnixStream::connect(&path).map_err(|e| {
+        anyhow::anyhow!(
+            "unable to reach a running instance at '{}': {e}",
+            path.to_string_lossy()
+        )
+    })?;
+    serde_json::to_writer(&mut stream, request)?;
+    stream.write_all(b"\n")?;
+    let mut line = String::new();
+    BufReader::new(stream).read_line(&mut line)?;
+    let response: Response = serde_json::from_str(&line)?;
+    match response.error {
+        Some(error) => anyhow::bail!(error),
+        None => Ok(response.value),
+    }
+}
+
+/// Convert JSON to the closest slint value.  Arrays become models and objects become structs.
+pub fn to_slint(value: serde_json::Value) -> Value {
+    match value {
+        serde_json::Value::Null => Value::Void,
+        serde_json::Value::Bool(b) => Value::Bool(b),
+        serde_json::Value::Number(n) => Value::Number(n.as_f64().unwrap_or_default()),
+        serde_json::Value::String(s) => Value::String(s.into()),
+        serde_json::Value::Array(values) => Value::Model(ModelRc::new(VecModel::from(
+            values.into_iter().map(to_slint).collect::<Vec<_>>(),
+        ))),
+        serde_json::Value::Object(fields) => Value::Struct(
+            fields
+                .into_iter()
+                .map(|(name, value)| (name, to_slint(value)))
+                .collect::<Struct>(),
+        ),
+    }
+}
+
+/// Convert a slint value to JSON.  Fails for values without a JSON equivalent, like images.
+pub fn from_slint(value: &Value) -> anyhow::Result<serde_json::Value> {
+    Ok(match value {
+        Value::Void => serde_json::Value::Null,
+        Value::Bool(b) => serde_json::Value::Bool(*b),
+        Value::Number(n) => {
+            serde_json::Number::from_f64(*n).map_or(serde_json::Value::Null, Into::into)
+        }
+        Value::String(s) => serde_json::Value::String(s.to_string()),
+        Value::Model(model) => serde_json::Value::Array(
+            model
+                .iter()
+                .map(|v| from_slint(&v))
+                .collect::<anyhow::Result<_>>()?,
+        ),
+        Value::Struct(fields) => serde_json::Value::Object(
+            fields
+                .iter()
+                // slint stores `read_rate` as `read-rate`, but the config and the properties
+                // are written with underscores
+                .map(|(name, value)| Ok((name.replace('-', "_"), from_slint(value)?)))
+                .collect::<anyhow::Result<_>>()?,
+        ),
+        value => anyhow::bail!("{:?} can't be represented as JSON", value.value_type()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn request_shape() {
+        let request: Request =
+            serde_json::from_str(r#"{"command": "set_property", "name": "time", "value": "noon"}"#)
+                .unwrap();
+        assert!(matches!(
+            request,
+            Request::SetProperty { name, value, bar: None } if name == "time" && value == "noon"
+        ));
+        let request: Request =
+            serde_json::from_str(r#"{"command": "get_global", "global": "Osd", "name": "value"}"#)
+                .unwrap();
+        assert!(matches!(request, Request::GetGlobal { bar: 0, .. }));
+        assert_eq!(
+            serde_json::to_value(Request::Refresh { script: None }).unwrap(),
+            json!({"command": "refresh", "script": null})
+        );
+        let request: Request =
+            serde_json::from_str(r#"{"command": "auto_hide", "enabled": false, "bar": 1}"#)
+                .unwrap();
+        assert!(matches!(
+            request,
+            Request::AutoHide {
+                enabled: false,
+                bar: Some(1)
+            }
+        ));
+        assert!(serde_json::from_str::<Request>(r#"{"command": "explode"}"#).is_err());
+    }
+
+    #[test]
+    fn message_values() {
+        let set = |value: &str| {
+            Request::from(cli::Message::Set {
+                name: "level".to_owned(),
+                value: value.to_owned(),
+                global: None,
+                bar: None,
+            })
+        };
+        assert!(matches!(set("0.5"), Request::SetProperty { value, .. } if value == json!(0.5)));
+        assert!(
+            matches!(set("[1, 2]"), Request::SetProperty { value, .. } if value == json!([1, 2]))
+        );
+        // not JSON, so taken as a string
+        assert!(matches!(set("noon"), Request::SetProperty { value, .. } if value == "noon"));
+        let request = Request::from(cli::Message::Set {
+            name: "shown".to_owned(),
+            value: "true".to_owned(),
+            global: Some("Processes".to_owned()),
+            bar: Some(1),
+        });
+        assert!(matches!(
+            request,
+            Request::SetGlobal { global, value, bar: Some(1), .. }
+                if global == "Processes" && value == json!(true)
+        ));
+    }
+
+    #[test]
+    fn slint_round_trip() {
+        // floats, as slint numbers are
+        let value = json!({
+            "name": "sda",
+            "busy": true,
+            "read_rate": 1.5,
+            "partitions": [{"name": "sda1", "ratio": 0.25}, {"name": "sda2", "ratio": 0.75}],
+            "history": [0.5, 2.5],
+            "icon": null,
+        });
+        let slint = to_slint(value.clone());
+        assert!(matches!(&slint, Value::Struct(_)));
+        assert_eq!(from_slint(&slint).unwrap(), value);
+        assert_eq!(from_slint(&to_slint(json!([]))).unwrap(), json!([]));
+        assert!(from_slint(&Value::Image(slint::Image::default())).is_err());
+    }
+}
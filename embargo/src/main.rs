@@ -1,4 +1,6 @@
 #![warn(clippy::pedantic, clippy::perf)]
+use std::{path::PathBuf, rc::Rc, time::Duration};
+
 use clap::Parser;
 use slint::{platform::software_renderer::MinimalSoftwareWindow, PhysicalSize};
 pub type Window = std::rc::Rc<MinimalSoftwareWindow>;
@@ -6,10 +8,25 @@ pub type Window = std::rc::Rc<MinimalSoftwareWindow>;
 mod cli;
 mod config;
 mod error;
+mod event_loop;
+mod format;
 mod hardware_mon;
+mod instance;
+mod ipc;
+mod osd;
+mod provider;
 mod run;
+mod scripts;
+mod signals;
+mod systemd;
+#[cfg(test)]
+mod temp_dir;
 
-use layer_platform::{Bar, LayerShellPlatform, RgbaPixel};
+use layer_platform::{
+    AutoHide, Bar, BarDefinition, Headless, LayerShellPlatform, RgbaPixel, SurfaceKind, WindowQueue,
+};
+use slint::ComponentHandle;
+use slint_interpreter::ComponentInstance;
 
 fn setup_logger(
     log_level: tracing::Level,
@@ -23,19 +40,24 @@ fn setup_logger(
     )
 }
 
-fn main() -> anyhow::Result<()> {
-    let args = cli::Cli::parse();
-    setup_logger(args.log_level)?;
-
-    let conf = config::Config::parse(args.override_config.as_deref())?;
+fn load_ui(slint_entrypoint: &std::path::Path) -> anyhow::Result<ComponentInstance> {
+    let mut compiler = slint_interpreter::ComponentCompiler::new();
+    let slint_src = spin_on::spin_on(compiler.build_from_path(slint_entrypoint));
+    create_component(&compiler, slint_src, slint_entrypoint)
+}
 
-    let (width, height) = (1920, 40);
-    let window = MinimalSoftwareWindow::new(
-        slint::platform::software_renderer::RepaintBufferType::ReusedBuffer,
-    );
-    slint::platform::set_platform(Box::new(LayerShellPlatform::new(window.clone()))).unwrap();
+/// Like [`load_ui`] for slint code built into embargo.  `path` is only used in diagnostics.
+fn load_ui_from_source(source: &str, path: &std::path::Path) -> anyhow::Result<ComponentInstance> {
     let mut compiler = slint_interpreter::ComponentCompiler::new();
-    let slint_src = spin_on::spin_on(compiler.build_from_path(&conf.slint_entrypoint));
+    let slint_src = spin_on::spin_on(compiler.build_from_source(source.into(), path.into()));
+    create_component(&compiler, slint_src, path)
+}
+
+fn create_component(
+    compiler: &slint_interpreter::ComponentCompiler,
+    slint_src: Option<slint_interpreter::ComponentDefinition>,
+    slint_entrypoint: &std::path::Path,
+) -> anyhow::Result<ComponentInstance> {
     for diagnostic in compiler.diagnostics() {
         use slint_interpreter::DiagnosticLevel;
         match diagnostic.level() {
@@ -44,25 +66,182 @@ fn main() -> anyhow::Result<()> {
             _ => unreachable!(),
         }
     }
-    let ui = slint_src.unwrap().create()?;
+    let Some(slint_src) = slint_src else {
+        anyhow::bail!("failed to compile '{}'", slint_entrypoint.to_string_lossy());
+    };
+    Ok(slint_src.create()?)
+}
 
-    // #[cfg(feature = "hyprland")]
-    // ui.global::<Workspaces>()
-    // .on_change_workspace(|id| hyprland_workspaces::change_workspace(id as u32).unwrap());
-    window.set_size(PhysicalSize::new(width, height));
-    let (bar, event_queue) = Bar::new(
-        window.clone(),
-        RgbaPixel::default(),
-        conf.anchor,
-        &conf.layer_name,
-        width,
-        height,
-    )?;
-    match args.command.unwrap_or(cli::Command::Run) {
-        cli::Command::Run => run::run(&ui, bar, event_queue, &window, width)?,
+/// Auto-hide settings that let the slint content animate through its `revealed` property
+fn auto_hide_for(config: &config::AutoHideConfig, ui: &ComponentInstance) -> AutoHide {
+    let ui = ui.as_weak();
+    AutoHide {
+        enabled: true,
+        delay: Duration::from_millis(config.delay_ms),
+        trigger_size: config.trigger_size,
+        animation: Duration::from_millis(config.animation_ms),
+        on_reveal: Some(Box::new(move |revealed| {
+            let Some(ui) = ui.upgrade() else {
+                return;
+            };
+            match ui.set_property("revealed", revealed.into()) {
+                Ok(()) | Err(slint_interpreter::SetPropertyError::NoSuchProperty) => {}
+                Err(e) => tracing::warn!("failed to set 'revealed': {e}"),
+            }
+        })),
+    }
+}
+
+fn main() -> anyhow::Result<()> {
+    let args = cli::Cli::parse();
+    setup_logger(args.log_level)?;
+
+    let parse_config = || {
+        let conf = config::Config::parse(args.override_config.as_deref())?;
+        tracing::debug!("loaded config from '{}'", conf.path.to_string_lossy());
+        anyhow::Ok(conf)
+    };
+    match args.command.clone().unwrap_or(cli::Command::Run {
+        windowed: false,
+        replace: false,
+    }) {
+        cli::Command::Run { windowed, replace } => {
+            let _lock =
+                instance::Lock::acquire(&instance::runtime_dir()?, args.name.as_deref(), replace)?;
+            let result = start(args.override_config, args.name.as_deref(), windowed);
+            // the logs go to stdout, which may be a pipe to the journal
+            std::io::Write::flush(&mut std::io::stdout())?;
+            result?;
+        }
         cli::Command::PrintConfig => {
-            println!("{conf:#?}");
+            println!("{:#?}", parse_config()?);
+        }
+        cli::Command::Screenshot { out, size, bar } => {
+            screenshot(&parse_config()?, &out, size, bar)?;
+        }
+        cli::Command::Msg { message } => {
+            if let Some(value) = ipc::send(args.name.as_deref(), &message.into())? {
+                println!("{value}");
+            }
         }
     }
     Ok(())
 }
+
+/// Render one bar with a single round of live data into a png
+fn screenshot(
+    conf: &config::Config,
+    out: &std::path::Path,
+    (width, height): (u32, u32),
+    bar: usize,
+) -> anyhow::Result<()> {
+    let Some(bar_conf) = conf.bars.get(bar) else {
+        anyhow::bail!("there is no bar {bar}, {} are configured", conf.bars.len());
+    };
+    let window = MinimalSoftwareWindow::new(
+        slint::platform::software_renderer::RepaintBufferType::NewBuffer,
+    );
+    slint::platform::set_platform(Box::new(LayerShellPlatform::new([window.clone()]))).unwrap();
+    let ui = load_ui(&bar_conf.slint_entrypoint)?;
+    run::publish_once(&ui, &conf.hardware);
+    ui.show()?;
+    let mut headless = Headless::new(window, RgbaPixel::default(), width, height);
+    headless.render();
+    headless.save_png(out)?;
+    tracing::info!("saved screenshot to '{}'", out.to_string_lossy());
+    Ok(())
+}
+
+/// Connect to the compositor and run the configured bars until exit
+fn start(config_path: Option<PathBuf>, name: Option<&str>, windowed: bool) -> anyhow::Result<()> {
+    let platform = LayerShellPlatform::new([]);
+    let window_queue = platform.windows();
+    slint::platform::set_platform(Box::new(platform)).unwrap();
+    let kind = if windowed {
+        SurfaceKind::Windowed
+    } else {
+        SurfaceKind::Layer
+    };
+    let (bar, event_queue) = Bar::new(Vec::new(), kind)?;
+    run::run(bar, event_queue, name, move |bar, waker| {
+        let conf = config::Config::parse(config_path.as_deref())?;
+        load(conf, bar, &window_queue, waker).inspect_err(|_| {
+            // don't hand windows meant for a component that failed to load to the next one
+            window_queue.clear();
+        })
+    })
+}
+
+/// Create a window and a slint component for every configured bar and the OSD, and add them to
+/// `bar`
+fn load(
+    conf: config::Config,
+    bar: &mut Bar,
+    window_queue: &WindowQueue,
+    waker: &event_loop::Waker,
+) -> anyhow::Result<run::Loaded> {
+    let new_window = |width, height| {
+        let window = MinimalSoftwareWindow::new(
+            slint::platform::software_renderer::RepaintBufferType::NewBuffer,
+        );
+        window.set_size(PhysicalSize::new(width, height));
+        window_queue.push(window.clone());
+        window
+    };
+    let windows = conf
+        .bars
+        .iter()
+        .map(|bar| new_window(bar.width, bar.height))
+        .collect::<Vec<_>>();
+    let osd_window = new_window(conf.osd.width, conf.osd.height);
+    // components pick up the windows in order, so they have to be created in the same order
+    let uis = conf
+        .bars
+        .iter()
+        .map(|bar| load_ui(&bar.slint_entrypoint))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    let osd_ui = osd::Osd::load_ui(&conf.osd)?;
+    let osd = Rc::new(osd::Osd::new(osd_ui, osd_window, &conf.osd, bar)?);
+
+    // #[cfg(feature = "hyprland")]
+    // ui.global::<Workspaces>()
+    // .on_change_workspace(|id| hyprland_workspaces::change_workspace(id as u32).unwrap());
+    let bars = conf
+        .bars
+        .into_iter()
+        .zip(windows)
+        .zip(&uis)
+        .map(|((bar_conf, window), ui)| {
+            BarDefinition {
+                window,
+                start_pixel: RgbaPixel::default(),
+                position: bar_conf.anchor,
+                layer_name: bar_conf.layer_name,
+                outputs: bar_conf.outputs,
+                width: bar_conf.width,
+                height: bar_conf.height,
+                auto_hide: bar_conf
+                    .auto_hide
+                    .map(|auto_hide| auto_hide_for(&auto_hide, ui)),
+                input_region: bar_conf.input_region,
+            }
+            .builder()
+            .build(bar)
+        })
+        .collect();
+    // dropping it takes the windows off the screen again if connecting the uis fails
+    let loaded = run::Loaded {
+        uis,
+        bars,
+        osd,
+        scripts: scripts::Scripts::new(conf.scripts, waker.clone()),
+        hardware: conf.hardware,
+    };
+    for ui in &loaded.uis {
+        loaded.osd.connect(ui)?;
+        run::connect_processes(ui)?;
+        ui.show()?;
+    }
+    tracing::debug!("loaded config from '{}'", conf.path.to_string_lossy());
+    Ok(loaded)
+}
@@ -0,0 +1,98 @@
+use std::{path::Path, rc::Rc, time::Duration};
+
+use layer_platform::{Bar, InputRegion, Layer, LayerWindow, LayerWindowBuilder, RgbaPixel};
+use slint::{ComponentHandle, Timer, TimerMode};
+use slint_interpreter::{ComponentInstance, SetCallbackError, SetPropertyError, Value};
+
+use crate::{config::OsdConfig, Window};
+
+/// The on-screen display used when the config doesn't name one
+const DEFAULT_SOURCE: &str = include_str!("osd.slint");
+
+/// A popup in the middle of the focused output that shows the level of something that just
+/// changed, like the volume or brightness.  It hides itself again after a timeout.
+pub struct Osd {
+    ui: ComponentInstance,
+    layer_window: LayerWindow,
+    timer: Timer,
+    timeout: Duration,
+}
+
+impl Osd {
+    /// Compile the configured component, or the built-in one.  The platform hands out windows in
+    /// order, so this has to happen after the bars are loaded.
+    pub fn load_ui(config: &OsdConfig) -> anyhow::Result<ComponentInstance> {
+        match &config.slint_entrypoint {
+            Some(path) => crate::load_ui(path),
+            None => crate::load_ui_from_source(DEFAULT_SOURCE, Path::new("osd.slint")),
+        }
+    }
+    /// Add the OSD to `bar`, hidden until [`Osd::show`] is called
+    pub fn new(
+        ui: ComponentInstance,
+        window: Window,
+        config: &OsdConfig,
+        bar: &mut Bar,
+    ) -> anyhow::Result<Self> {
+        // before the window is added, so failing doesn't leave it behind
+        ui.show()?;
+        let layer_window = LayerWindowBuilder::new(window, config.width, config.height)
+            .namespace(format!("{}_osd", clap::crate_name!()))
+            .layer(Layer::Overlay)
+            .focused_output(true)
+            .start_pixel(RgbaPixel::transparent())
+            // never get in the way of what is below
+            .input_region(InputRegion::Rects(Vec::new()))
+            .visible(false)
+            .build(bar);
+        Ok(Self {
+            ui,
+            layer_window,
+            timer: Timer::default(),
+            timeout: Duration::from_millis(config.timeout_ms),
+        })
+    }
+    /// Show `value`, between 0 and 1, next to `icon`.  Showing it again while it is up restarts
+    /// the timeout.
+    pub fn show(&self, icon: &str, value: f32) {
+        for (name, value) in [
+            ("icon-name", Value::String(icon.into())),
+            ("value", Value::Number(value.into())),
+        ] {
+            match self.ui.set_property(name, value) {
+                Ok(()) | Err(SetPropertyError::NoSuchProperty) => {}
+                Err(e) => tracing::warn!("failed to set '{name}' on the osd: {e}"),
+            }
+        }
+        self.layer_window.show();
+        let layer_window = self.layer_window.clone();
+        self.timer
+            .start(TimerMode::SingleShot, self.timeout, move || {
+                layer_window.hide();
+            });
+    }
+    /// Let `ui` show the OSD through the `show(icon, value)` callback of its `Osd` global
+    pub fn connect(self: &Rc<Self>, ui: &ComponentInstance) -> anyhow::Result<()> {
+        let osd = Rc::downgrade(self);
+        let result = ui.set_global_callback("Osd", "show", move |args| {
+            let (Some(osd), [Value::String(icon), Value::Number(value)]) = (osd.upgrade(), args)
+            else {
+                tracing::warn!("Osd.show expects an icon and a value, got {args:?}");
+                return Value::Void;
+            };
+            #[allow(clippy::cast_possible_truncation)]
+            osd.show(icon, *value as f32);
+            Value::Void
+        });
+        match result {
+            Ok(()) | Err(SetCallbackError::NoSuchCallback) => Ok(()),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+impl Drop for Osd {
+    fn drop(&mut self) {
+        self.layer_window.destroy();
+    }
+}
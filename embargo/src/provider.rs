@@ -0,0 +1,141 @@
+//! Keeps one failing data source from taking down the rest of the bar.  Every provider, e.g. the
+//! workspaces or a script, updates through [`Providers::update`].  A failed update publishes
+//! nothing, so the bar keeps showing the last good value.  The status of each provider is
+//! published to the `Providers` global as `<name>-ok` and `<name>-error`, e.g.
+//!
+//! ```slint
+//! export global Providers {
+//!     in property <bool> workspaces-ok: true;
+//!     in property <string> workspaces-error;
+//! }
+//! ```
+use std::{
+    collections::HashMap,
+    panic::AssertUnwindSafe,
+    time::{Duration, Instant},
+};
+
+/// How often an error that keeps happening is logged again
+const LOG_INTERVAL: Duration = Duration::from_mins(1);
+
+#[derive(Default)]
+pub struct Providers(HashMap<String, Provider>);
+
+#[derive(Default)]
+struct Provider {
+    error: Option<String>,
+    /// when `error` was last logged
+    last_logged: Option<Instant>,
+    /// whether the status changed since it was last published
+    changed: bool,
+}
+
+impl Providers {
+    /// Run `update` for the provider called `name`, and record whether it failed.  A panic counts
+    /// as a failure.
+    pub fn update<T>(
+        &mut self,
+        name: &str,
+        update: impl FnOnce() -> anyhow::Result<T>,
+    ) -> Option<T> {
+        let result = match std::panic::catch_unwind(AssertUnwindSafe(update)) {
+            Ok(result) => result.map_err(|e| format!("{e:#}")),
+            Err(panic) => Err(panic
+                .downcast_ref::<&str>()
+                .map(ToString::to_string)
+                .or_else(|| panic.downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| "panicked".to_owned())),
+        };
+        if !self.0.contains_key(name) {
+            // so the first status is published, too
+            let provider = Provider {
+                changed: true,
+                ..Provider::default()
+            };
+            self.0.insert(name.to_owned(), provider);
+        }
+        let provider = self.0.get_mut(name).unwrap();
+        match result {
+            Ok(value) => {
+                if provider.error.take().is_some() {
+                    tracing::info!("'{name}' recovered");
+                    provider.last_logged = None;
+                    provider.changed = true;
+                }
+                Some(value)
+            }
+            Err(error) => {
+                let now = Instant::now();
+                if provider.error.as_ref() != Some(&error) {
+                    tracing::warn!("'{name}' failed: {error}");
+                    provider.last_logged = Some(now);
+                    provider.error = Some(error);
+                    provider.changed = true;
+                } else if provider
+                    .last_logged
+                    .is_none_or(|t| now.duration_since(t) >= LOG_INTERVAL)
+                {
+                    tracing::warn!("'{name}' is still failing: {error}");
+                    provider.last_logged = Some(now);
+                }
+                None
+            }
+        }
+    }
+    /// The providers whose status changed since the last call, as `(name, error)`
+    pub fn take_changed(&mut self) -> Vec<(&str, Option<&str>)> {
+        self.0
+            .iter_mut()
+            .filter_map(|(name, provider)| {
+                std::mem::take(&mut provider.changed)
+                    .then_some((name.as_str(), provider.error.as_deref()))
+            })
+            .collect()
+    }
+    /// Publish every status again, e.g. to freshly loaded bars
+    pub fn mark_all_changed(&mut self) {
+        for provider in self.0.values_mut() {
+            provider.changed = true;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn status() {
+        let mut providers = Providers::default();
+        assert_eq!(providers.update("time", || Ok(1)), Some(1));
+        assert_eq!(providers.take_changed(), vec![("time", None)]);
+        assert!(providers.take_changed().is_empty());
+
+        let failed = providers.update("time", || -> anyhow::Result<()> {
+            anyhow::bail!("no clock")
+        });
+        assert_eq!(failed, None);
+        assert_eq!(providers.take_changed(), vec![("time", Some("no clock"))]);
+        // the same error again isn't a change
+        let _ = providers.update("time", || -> anyhow::Result<()> {
+            anyhow::bail!("no clock")
+        });
+        assert!(providers.take_changed().is_empty());
+
+        assert_eq!(providers.update("time", || Ok(2)), Some(2));
+        assert_eq!(providers.take_changed(), vec![("time", None)]);
+    }
+
+    #[test]
+    fn panic() {
+        let mut providers = Providers::default();
+        let result = providers.update("workspaces", || -> anyhow::Result<()> {
+            panic!("no socket")
+        });
+        assert_eq!(result, None);
+        assert_eq!(
+            providers.take_changed(),
+            vec![("workspaces", Some("no socket"))]
+        );
+    }
+}
@@ -1,96 +1,929 @@
-use crate::hardware_mon;
-use human_repr::HumanCount;
-use layer_platform::Bar;
-use slint::{platform::software_renderer::MinimalSoftwareWindow, ComponentHandle};
-use slint_interpreter::ComponentInstance;
+use std::{rc::Rc, time::Duration};
+
+use crate::{
+    config::{FormatConfig, HardwareConfig},
+    event_loop::{self, WakeReceiver, Waker},
+    hardware_mon::{
+        self,
+        history::History,
+        power, processes,
+        sensors::{self, Sensor},
+        Refreshed,
+    },
+    ipc::{self, Request, Response},
+    osd::Osd,
+    provider::Providers,
+    scripts::Scripts,
+    signals, systemd,
+};
+use layer_platform::{Bar, LayerWindow};
+use slint::{ComponentHandle, Model, ModelRc, VecModel};
+use slint_interpreter::{ComponentInstance, SetCallbackError, SetPropertyError, Struct, Value};
+
+/// Every bar shown by this process.  Data is only polled once and then published to all of them.
+/// A bar that doesn't declare a property simply doesn't receive it, so e.g. a bottom bar with only
+/// system stats doesn't need a `workspaces` property.
+struct Uis<'a>(&'a [ComponentInstance]);
+impl Uis<'_> {
+    fn set_property(&self, name: &str, value: &Value) -> Result<(), SetPropertyError> {
+        for ui in self.0 {
+            match ui.set_property(name, value.clone()) {
+                Ok(()) | Err(SetPropertyError::NoSuchProperty) => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+    fn set_global_property(
+        &self,
+        global: &str,
+        name: &str,
+        value: &Value,
+    ) -> Result<(), SetPropertyError> {
+        for ui in self.0 {
+            match ui.set_global_property(global, name, value.clone()) {
+                Ok(()) | Err(SetPropertyError::NoSuchProperty) => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+    /// Whether any ui has a global property that is true
+    fn any_global_bool(&self, global: &str, name: &str) -> bool {
+        self.0
+            .iter()
+            .any(|ui| matches!(ui.get_global_property(global, name), Ok(Value::Bool(true))))
+    }
+}
+
+/// Everything created from the config.  Dropping it takes the bars off the screen, so a reload
+/// simply replaces it.
+pub struct Loaded {
+    pub uis: Vec<ComponentInstance>,
+    pub bars: Vec<LayerWindow>,
+    pub osd: Rc<Osd>,
+    pub scripts: Scripts,
+    pub hardware: HardwareConfig,
+}
+
+impl Drop for Loaded {
+    fn drop(&mut self) {
+        for bar in &self.bars {
+            bar.destroy();
+        }
+    }
+}
+
+/// Load the config with `load` and run until the compositor closes the bars or a signal asks
+/// us to exit.  `load` is called again for every reload.  The control socket is named after the
+/// instance `name`.
 pub fn run(
-    // ui: MainUi,
-    ui: &ComponentInstance,
     mut bar: Bar,
     mut event_queue: layer_platform::EventQueue,
-    window: &std::rc::Rc<MinimalSoftwareWindow>,
-    width: u32,
+    name: Option<&str>,
+    load: impl Fn(&mut Bar, &Waker) -> anyhow::Result<Loaded>,
 ) -> anyhow::Result<()> {
-    let mut hw_mon = hardware_mon::HardwareMonitor::new("enp6s0".into());
+    let (waker, mut wake) = WakeReceiver::new()?;
+    signals::install(&waker)?;
+    let server = match systemd::activated_listener() {
+        Some(listener) => Ok(ipc::Server::listen(listener, None, waker.clone())),
+        None => ipc::Server::bind(name, waker.clone()),
+    };
+    let server = match server {
+        Ok(server) => Some(server),
+        Err(e) => {
+            tracing::warn!("running without a control socket: {e}");
+            None
+        }
+    };
+    let mut loaded = load(&mut bar, &waker)?;
+    let mut providers = Providers::default();
+    let mut hw_mon = hardware_mon::HardwareMonitor::new(&loaded.hardware);
     hw_mon.update();
-    #[cfg(feature = "hyprland")]
-    let mut workspaces;
-    let mut formatted_time;
-    let mut time;
-    ui.set_global_property(
-        "HardwareMonitor",
-        "totalmemory",
-        slint_interpreter::Value::String(
-            HumanCount::human_count_bytes(hw_mon.total_mem())
-                .to_string()
-                .into(),
-        ),
-    )?;
-    ui.show()?;
+    publish_static(
+        &Uis(&loaded.uis),
+        &hw_mon,
+        &loaded.hardware.format,
+        &mut providers,
+    );
+    hw_mon.mark_all_refreshed();
+    let mut watchdog = systemd::Watchdog::from_env();
+    let mut ready = false;
     loop {
-        event_queue.blocking_dispatch(&mut bar)?;
+        let timeout = [
+            loaded.scripts.timeout(),
+            slint::platform::duration_until_next_timer_update(),
+            watchdog.as_ref().map(systemd::Watchdog::timeout),
+        ]
+        .into_iter()
+        .flatten()
+        .min();
+        event_loop::dispatch(&mut event_queue, &mut bar, &mut wake, timeout)?;
         slint::platform::update_timers_and_animations();
-        hw_mon.update();
-        #[cfg(feature = "hyprland")]
-        {
-            workspaces = hyprland::Workspaces::new()?;
-        }
-
-        time = chrono::Local::now();
-        formatted_time = time.format("%I:%M%P -- %d of %b, %Y").to_string();
-
-        #[cfg(feature = "hyprland")]
-        // ui.set_workspaces());
-        ui.set_property("workspaces", workspaces.as_value())?;
-        ui.set_property(
-            "time",
-            slint_interpreter::Value::String(formatted_time.into()),
-        )?;
-        ui.set_global_property(
-            "HardwareMonitor",
-            "cpu_usage",
-            slint_interpreter::Value::String(
-                ((hw_mon.cpu_usage() * 10.0).round() / 10.0)
-                    .to_string()
-                    .into(),
-            ),
-        )?;
-        ui.set_global_property(
-            "HardwareMonitor",
-            "network_up",
-            slint_interpreter::Value::String(
-                human_repr::HumanThroughput::human_throughput_bytes(hw_mon.uploaded_bytes())
-                    .to_string()
-                    .into(),
-            ),
-        )?;
-        ui.set_global_property(
-            "HardwareMonitor",
-            "network_down",
-            slint_interpreter::Value::String(
-                human_repr::HumanThroughput::human_throughput_bytes(hw_mon.downloaded_bytes())
-                    .to_string()
-                    .into(),
-            ),
-        )?;
-        ui.set_global_property(
-            "HardwareMonitor",
-            "used_memory",
-            slint_interpreter::Value::String(
-                HumanCount::human_count_bytes(hw_mon.used_mem())
-                    .to_string()
-                    .into(),
-            ),
-        )?;
-        window.draw_if_needed(|renderer| {
-            renderer.render(&mut bar.software_buffer, width as usize);
-        });
+        let mut reload = |loaded: &mut Loaded, bar: &mut Bar| {
+            *loaded = load(bar, &waker)?;
+            hw_mon.configure(&loaded.hardware);
+            // replaced before the next round of data, so the first frame isn't empty
+            providers.mark_all_changed();
+            hw_mon.mark_all_refreshed();
+            publish_static(
+                &Uis(&loaded.uis),
+                &hw_mon,
+                &loaded.hardware.format,
+                &mut providers,
+            );
+            tracing::info!("reloaded config");
+            anyhow::Ok(())
+        };
+        if signals::take_reload() {
+            if let Err(e) = reload(&mut loaded, &mut bar) {
+                tracing::error!("failed to reload: {e}");
+            }
+        }
+        for (request, response) in server.iter().flat_map(ipc::Server::requests) {
+            let result = match request {
+                Request::Reload => reload(&mut loaded, &mut bar).map(|()| Response::default()),
+                request => handle(&mut loaded, request),
+            };
+            // the client may have hung up already
+            let _ = response.send(result.unwrap_or_else(|e| Err(e).into()));
+        }
+        let ui = Uis(&loaded.uis);
+        for (name, output) in loaded.scripts.poll() {
+            providers.update(&name, || {
+                ui.set_property(&name, &Value::String(output?.into()))?;
+                Ok(())
+            });
+        }
+        hw_mon.set_processes_shown(ui.any_global_bool("Processes", "shown"));
+        let refreshed = hw_mon.update();
+        publish(
+            &ui,
+            &hw_mon,
+            refreshed,
+            &loaded.hardware.format,
+            &mut providers,
+        );
+        bar.render();
+        if !ready && bar.all_drawn() {
+            systemd::notify("READY=1");
+            ready = true;
+        }
+        if let Some(watchdog) = &mut watchdog {
+            watchdog.ping_if_due();
+        }
+        if let Some(signal) = signals::exit_requested() {
+            tracing::info!("received {signal}, exiting");
+            break;
+        }
         if bar.exit {
             break;
         }
     }
+    // terminates the scripts
+    drop(loaded);
+    bar.close();
+    event_queue.flush()?;
+    Ok(())
+}
+
+/// Answer a request from the control socket
+fn handle(loaded: &mut Loaded, request: Request) -> anyhow::Result<Response> {
+    let value = match request {
+        Request::SetProperty { name, value, bar } => {
+            let value = ipc::to_slint(value);
+            set_on_any(select(&loaded.uis, bar)?, |ui| {
+                ui.set_property(&name, value.clone())
+            })?;
+            None
+        }
+        Request::SetGlobal {
+            global,
+            name,
+            value,
+            bar,
+        } => {
+            let value = ipc::to_slint(value);
+            set_on_any(select(&loaded.uis, bar)?, |ui| {
+                ui.set_global_property(&global, &name, value.clone())
+            })?;
+            None
+        }
+        Request::GetProperty { name, bar } => {
+            let ui = &select(&loaded.uis, Some(bar))?[0];
+            Some(ipc::from_slint(&ui.get_property(&name)?)?)
+        }
+        Request::GetGlobal { global, name, bar } => {
+            let ui = &select(&loaded.uis, Some(bar))?[0];
+            Some(ipc::from_slint(&ui.get_global_property(&global, &name)?)?)
+        }
+        Request::Refresh { script } => {
+            loaded.scripts.refresh(script.as_deref())?;
+            None
+        }
+        Request::Show { output, bar } => {
+            set_visible(select(&loaded.bars, bar)?, output.as_deref(), |_| true);
+            None
+        }
+        Request::Hide { output, bar } => {
+            set_visible(select(&loaded.bars, bar)?, output.as_deref(), |_| false);
+            None
+        }
+        Request::Toggle { output, bar } => {
+            set_visible(select(&loaded.bars, bar)?, output.as_deref(), |visible| {
+                !visible
+            });
+            None
+        }
+        Request::AutoHide { enabled, bar } => {
+            for window in select(&loaded.bars, bar)? {
+                window.set_auto_hide(enabled);
+            }
+            None
+        }
+        Request::Osd { icon, value } => {
+            loaded.osd.show(&icon, value);
+            None
+        }
+        Request::Reload => unreachable!("reloads replace what is loaded"),
+    };
+    Ok(Response { value, error: None })
+}
+
+/// The bar with index `bar`, or all of them
+fn select<T>(items: &[T], bar: Option<usize>) -> anyhow::Result<&[T]> {
+    match bar {
+        Some(bar) => items
+            .get(bar..=bar)
+            .ok_or_else(|| anyhow::anyhow!("there is no bar {bar}")),
+        None => Ok(items),
+    }
+}
+
+/// Set something on every ui that has it.  Fails if none of them has it.
+fn set_on_any(
+    uis: &[ComponentInstance],
+    set: impl Fn(&ComponentInstance) -> Result<(), SetPropertyError>,
+) -> anyhow::Result<()> {
+    let mut found = false;
+    for ui in uis {
+        match set(ui) {
+            Ok(()) => found = true,
+            Err(SetPropertyError::NoSuchProperty) => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+    anyhow::ensure!(found, "no bar has that property");
     Ok(())
 }
+
+/// Change whether each of `windows` is shown on `output`, or at all, depending on whether it
+/// currently is
+fn set_visible(windows: &[LayerWindow], output: Option<&str>, visible: impl Fn(bool) -> bool) {
+    for window in windows {
+        match output {
+            Some(output) if visible(window.is_visible_on(output)) => window.show_on(output),
+            Some(output) => window.hide_on(output),
+            None if visible(window.is_visible()) => window.show(),
+            None => window.hide(),
+        }
+    }
+}
+
+/// Poll every provider once and publish the results to `ui`
+pub fn publish_once(ui: &ComponentInstance, hardware: &HardwareConfig) {
+    let ui = Uis(std::slice::from_ref(ui));
+    let mut providers = Providers::default();
+    let mut hw_mon = hardware_mon::HardwareMonitor::new(hardware);
+    hw_mon.set_processes_shown(ui.any_global_bool("Processes", "shown"));
+    hw_mon.update();
+    publish_static(&ui, &hw_mon, &hardware.format, &mut providers);
+    publish(
+        &ui,
+        &hw_mon,
+        Refreshed::all(),
+        &hardware.format,
+        &mut providers,
+    );
+}
+
+/// Set a property of the `HardwareMonitor` global
+fn set_hw(ui: &Uis, name: &str, value: impl Into<Value>) -> Result<(), SetPropertyError> {
+    ui.set_global_property("HardwareMonitor", name, &value.into())
+}
+
+/// A slint struct of `fields`.  A struct declared in slint needs every one of its fields, or
+/// setting the property fails, so the fields documented on the publishers below are all set, with
+/// unknown values as empty strings or 0.  Sizes, rates and percentages come twice: formatted as a
+/// string under the plain name, and as a float for bindings that compute with them.
+fn struct_value<'a>(fields: impl IntoIterator<Item = (&'a str, Value)>) -> Value {
+    fields
+        .into_iter()
+        .map(|(field, value)| (field.to_owned(), value))
+        .collect::<Struct>()
+        .into()
+}
+
+/// A slint model of `values`, for `[T]` properties
+fn model(values: Vec<Value>) -> Value {
+    Value::Model(ModelRc::new(VecModel::from(values)))
+}
+
+/// Publish values that don't change while running
+#[allow(clippy::cast_precision_loss)]
+fn publish_static(
+    ui: &Uis,
+    hw_mon: &hardware_mon::HardwareMonitor,
+    format: &FormatConfig,
+    providers: &mut Providers,
+) {
+    providers.update("memory", || {
+        let total = hw_mon.total_mem() as f64;
+        set_hw(ui, "totalmemory", Value::String(format.bytes(total).into()))?;
+        set_hw(ui, "total_memory_bytes", total)?;
+        Ok(())
+    });
+    publish_status(ui, providers);
+}
+
+/// Publish the latest data.  A provider that fails keeps its last value on screen.  Next to each
+/// formatted string there is the raw number: percentages as floats from 0 to 100, sizes and rates
+/// in bytes and bytes per second, durations in seconds, and ratios from 0 to 1.  Hardware
+/// providers only run when `refreshed` says their data changed.
+fn publish(
+    ui: &Uis,
+    hw_mon: &hardware_mon::HardwareMonitor,
+    refreshed: Refreshed,
+    format: &FormatConfig,
+    providers: &mut Providers,
+) {
+    #[cfg(feature = "hyprland")]
+    providers.update("workspaces", || {
+        let workspaces = hyprland::Workspaces::new()?;
+        ui.set_property("workspaces", &workspaces.as_value())?;
+        Ok(())
+    });
+    providers.update("time", || {
+        let time = chrono::Local::now();
+        let formatted_time = time.format("%I:%M%P -- %d of %b, %Y").to_string();
+        ui.set_property("time", &Value::String(formatted_time.into()))?;
+        Ok(())
+    });
+    if refreshed.cpu {
+        providers.update("cpu", || {
+            let usage = hw_mon.cpu_usage();
+            set_hw(
+                ui,
+                "cpu_usage",
+                Value::String(format.percent(usage.into()).into()),
+            )?;
+            set_hw(ui, "cpu_usage_percent", usage)?;
+            set_hw(ui, "cores", cores_value(hw_mon, format))?;
+            Ok(())
+        });
+    }
+    if refreshed.histories {
+        providers.update("history", || {
+            let histories = hw_mon.histories();
+            set_history(ui, "cpu", &histories.cpu, 100.0)?;
+            set_history(ui, "memory", &histories.memory, 100.0)?;
+            // the same scale for both, so they can be compared
+            let max = histories.network_up.max().max(histories.network_down.max());
+            set_history(ui, "network_up", &histories.network_up, max)?;
+            set_history(ui, "network_down", &histories.network_down, max)?;
+            Ok(())
+        });
+    }
+    if refreshed.network {
+        providers.update("network", || {
+            set_hw(
+                ui,
+                "networks",
+                interfaces_value(hw_mon.interfaces(), format),
+            )?;
+            let (up, down) = (hw_mon.uploaded_bytes()?, hw_mon.downloaded_bytes()?);
+            set_hw(ui, "network_up", Value::String(format.rate(up).into()))?;
+            set_hw(ui, "network_up_bytes", up)?;
+            set_hw(ui, "network_down", Value::String(format.rate(down).into()))?;
+            set_hw(ui, "network_down_bytes", down)?;
+            Ok(())
+        });
+    }
+    if refreshed.memory || refreshed.stats {
+        providers.update("memory", || {
+            publish_memory(ui, hw_mon, format)?;
+            Ok(())
+        });
+    }
+    if refreshed.stats {
+        providers.update("system", || {
+            publish_system(ui, hw_mon, format)?;
+            Ok(())
+        });
+    }
+    if refreshed.disks {
+        providers.update("disks", || {
+            set_hw(ui, "disks", disks_value(hw_mon.disks(), format))?;
+            Ok(())
+        });
+    }
+    if refreshed.disk_io {
+        providers.update("disk_io", || {
+            set_hw(ui, "disk_io", disk_io_value(hw_mon.disk_io()?, format))?;
+            Ok(())
+        });
+    }
+    if refreshed.processes && hw_mon.processes_shown() {
+        providers.update("processes", || {
+            publish_processes(ui, hw_mon.top_processes(), format)?;
+            Ok(())
+        });
+    }
+    if refreshed.power {
+        providers.update("battery", || {
+            publish_battery(ui, hw_mon, format)?;
+            Ok(())
+        });
+    }
+    if refreshed.sensors {
+        providers.update("sensors", || {
+            publish_sensors(ui, hw_mon.sensors()?, format)?;
+            Ok(())
+        });
+    }
+    publish_status(ui, providers);
+}
+
+/// Publish the memory and swap to `HardwareMonitor`, each as a string and in bytes with the
+/// `_bytes` suffix, and how full they are as `memory_usage_ratio` and `swap_usage_ratio`
+#[allow(clippy::cast_precision_loss)]
+fn publish_memory(
+    ui: &Uis,
+    hw_mon: &hardware_mon::HardwareMonitor,
+    format: &FormatConfig,
+) -> anyhow::Result<()> {
+    let used = hw_mon.used_mem() as f64;
+    set_hw(ui, "used_memory", Value::String(format.bytes(used).into()))?;
+    set_hw(ui, "used_memory_bytes", used)?;
+    set_hw(
+        ui,
+        "memory_usage_ratio",
+        used / hw_mon.total_mem().max(1) as f64,
+    )?;
+    let available = hw_mon.available_mem() as f64;
+    set_hw(
+        ui,
+        "available_memory",
+        Value::String(format.bytes(available).into()),
+    )?;
+    set_hw(ui, "available_memory_bytes", available)?;
+    let (used_swap, total_swap) = (hw_mon.used_swap() as f64, hw_mon.total_swap() as f64);
+    set_hw(
+        ui,
+        "used_swap",
+        Value::String(format.bytes(used_swap).into()),
+    )?;
+    set_hw(ui, "used_swap_bytes", used_swap)?;
+    set_hw(
+        ui,
+        "total_swap",
+        Value::String(format.bytes(total_swap).into()),
+    )?;
+    set_hw(ui, "total_swap_bytes", total_swap)?;
+    // 0 without swap rather than NaN
+    set_hw(ui, "swap_usage_ratio", used_swap / total_swap.max(1.0))?;
+    let cached = hw_mon.cached_mem()? as f64;
+    set_hw(
+        ui,
+        "cached_memory",
+        Value::String(format.bytes(cached).into()),
+    )?;
+    set_hw(ui, "cached_memory_bytes", cached)?;
+    Ok(())
+}
+
+/// Publish the load average to `HardwareMonitor` as `load_average` and as the floats
+/// `load_average_1`, `load_average_5` and `load_average_15`, the uptime as `uptime` and
+/// `uptime_seconds`, and the number of processes as `processes` and `processes_count`
+#[allow(clippy::cast_precision_loss)]
+fn publish_system(
+    ui: &Uis,
+    hw_mon: &hardware_mon::HardwareMonitor,
+    format: &FormatConfig,
+) -> anyhow::Result<()> {
+    let load = hw_mon.load_average();
+    let loads = [load.one, load.five, load.fifteen];
+    set_hw(
+        ui,
+        "load_average",
+        Value::String(loads.map(|load| format.number(load)).join(" ").into()),
+    )?;
+    for (minutes, load) in [1, 5, 15].into_iter().zip(loads) {
+        set_hw(ui, &format!("load_average_{minutes}"), load)?;
+    }
+    let uptime = hw_mon.uptime();
+    set_hw(
+        ui,
+        "uptime",
+        Value::String(FormatConfig::duration(uptime).into()),
+    )?;
+    set_hw(ui, "uptime_seconds", uptime.as_secs_f64())?;
+    let processes = hw_mon.processes()? as f64;
+    set_hw(ui, "processes", Value::String(processes.to_string().into()))?;
+    set_hw(ui, "processes_count", processes)?;
+    Ok(())
+}
+
+/// Publish the processes using the most cpu and memory to the `Processes` global as `by_cpu`
+/// and `by_memory`.  Each process has `pid` as an int, the strings `name`, `user`, `cpu` and
+/// `memory`, and the floats `cpu_percent` and `memory_bytes`.
+#[allow(clippy::cast_precision_loss)]
+fn publish_processes(
+    ui: &Uis,
+    top: &processes::Top,
+    format: &FormatConfig,
+) -> Result<(), SetPropertyError> {
+    let list = |processes: &[processes::Process]| {
+        let processes = processes
+            .iter()
+            .map(|process| {
+                struct_value([
+                    ("pid", Value::Number(process.pid.into())),
+                    ("name", Value::String(process.name.as_str().into())),
+                    ("user", Value::String(process.user.as_str().into())),
+                    (
+                        "cpu",
+                        Value::String(format.percent(process.cpu.into()).into()),
+                    ),
+                    ("cpu_percent", process.cpu.into()),
+                    (
+                        "memory",
+                        Value::String(format.bytes(process.memory as f64).into()),
+                    ),
+                    ("memory_bytes", (process.memory as f64).into()),
+                ])
+            })
+            .collect::<Vec<Value>>();
+        model(processes)
+    };
+    ui.set_global_property("Processes", "by_cpu", &list(&top.by_cpu))?;
+    ui.set_global_property("Processes", "by_memory", &list(&top.by_memory))
+}
+
+/// Let `ui` signal the listed processes through the `send_signal(pid, signal)` callback of its
+/// `Processes` global, e.g. `Processes.send-signal(process.pid, "TERM")`.  Only the pids in the
+/// lists last published to `ui` can be signalled.
+pub fn connect_processes(ui: &ComponentInstance) -> anyhow::Result<()> {
+    // the callback is owned by the ui, so a strong reference would keep it alive forever
+    let weak = ui.as_weak();
+    let result = ui.set_global_callback("Processes", "send_signal", move |args| {
+        let (Some(ui), [Value::Number(pid), Value::String(signal)]) = (weak.upgrade(), args) else {
+            tracing::warn!("Processes.send-signal expects a pid and a signal, got {args:?}");
+            return Value::Void;
+        };
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        let pid = *pid as u32;
+        if !listed_pids(&ui).contains(&pid) {
+            tracing::warn!("refusing to send {signal} to {pid}, which isn't listed");
+            return Value::Void;
+        }
+        match processes::send_signal(pid, signal) {
+            Ok(()) => tracing::info!("sent {signal} to {pid}"),
+            Err(e) => tracing::warn!("failed to send {signal} to {pid}: {e}"),
+        }
+        Value::Void
+    });
+    match result {
+        Ok(()) | Err(SetCallbackError::NoSuchCallback) => Ok(()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// The pids in the `by_cpu` and `by_memory` lists of the `Processes` global of `ui`
+fn listed_pids(ui: &ComponentInstance) -> Vec<u32> {
+    ["by_cpu", "by_memory"]
+        .into_iter()
+        .filter_map(|list| match ui.get_global_property("Processes", list) {
+            Ok(Value::Model(model)) => Some(model),
+            _ => None,
+        })
+        .flat_map(|model| model.iter().collect::<Vec<_>>())
+        .filter_map(|process| match process {
+            Value::Struct(process) => match process.get_field("pid") {
+                #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+                Some(Value::Number(pid)) => Some(*pid as u32),
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect()
+}
+
+/// Publish the batteries and AC adapters to the `Battery` global: whether there are batteries
+/// as `present`, the charge of all of them as `capacity` and `capacity_percent`, their `status`,
+/// `time_remaining` and `time_remaining_seconds` until empty or full, `ac_online`, whether they
+/// are `low` or `critical`, and each of them in `batteries`.  Each battery has the strings
+/// `name`, `capacity`, `status`, `power`, `time_to_empty` and `time_to_full`, and the floats
+/// `capacity_percent`, `energy_now_wh`, `energy_full_wh`, `power_watts`, `time_to_empty_seconds`
+/// and `time_to_full_seconds`.
+fn publish_battery(
+    ui: &Uis,
+    hw_mon: &hardware_mon::HardwareMonitor,
+    format: &FormatConfig,
+) -> anyhow::Result<()> {
+    let power = hw_mon.power()?;
+    let duration = |duration: Option<Duration>| {
+        (
+            Value::String(
+                duration
+                    .map(FormatConfig::duration)
+                    .unwrap_or_default()
+                    .into(),
+            ),
+            Value::from(duration.map_or(0.0, |d| d.as_secs_f64())),
+        )
+    };
+    let batteries = power
+        .batteries
+        .iter()
+        .map(|battery| {
+            let (time_to_empty, time_to_empty_seconds) = duration(battery.time_to_empty);
+            let (time_to_full, time_to_full_seconds) = duration(battery.time_to_full);
+            struct_value([
+                ("name", Value::String(battery.name.as_str().into())),
+                (
+                    "capacity",
+                    Value::String(format.percent(battery.capacity).into()),
+                ),
+                ("capacity_percent", battery.capacity.into()),
+                ("status", Value::String(battery.status.as_str().into())),
+                (
+                    "energy_now_wh",
+                    battery.energy_now.unwrap_or_default().into(),
+                ),
+                (
+                    "energy_full_wh",
+                    battery.energy_full.unwrap_or_default().into(),
+                ),
+                (
+                    "power",
+                    Value::String(
+                        battery
+                            .power
+                            .map(|p| format.power(p))
+                            .unwrap_or_default()
+                            .into(),
+                    ),
+                ),
+                ("power_watts", battery.power.unwrap_or_default().into()),
+                ("time_to_empty", time_to_empty),
+                ("time_to_empty_seconds", time_to_empty_seconds),
+                ("time_to_full", time_to_full),
+                ("time_to_full_seconds", time_to_full_seconds),
+            ])
+        })
+        .collect::<Vec<Value>>();
+    let capacity = power.capacity().unwrap_or_default();
+    let (time_remaining, time_remaining_seconds) = duration(power.time_remaining());
+    let level = hw_mon.battery_level();
+    let set = |name: &str, value: Value| ui.set_global_property("Battery", name, &value);
+    set("present", Value::Bool(!power.batteries.is_empty()))?;
+    set("batteries", model(batteries))?;
+    set("capacity", Value::String(format.percent(capacity).into()))?;
+    set("capacity_percent", capacity.into())?;
+    set("status", Value::String(power.status().into()))?;
+    set("time_remaining", time_remaining)?;
+    set("time_remaining_seconds", time_remaining_seconds)?;
+    set("ac_online", Value::Bool(power.ac_online))?;
+    set("low", Value::Bool(level >= power::Level::Low))?;
+    set("critical", Value::Bool(level == power::Level::Critical))?;
+    Ok(())
+}
+
+/// Publish `sensors` to the `Sensors` global: the temperatures as `temperatures`, the fans as
+/// `fans`, whether any of them is critical as `critical`, and the hottest temperature as
+/// `max_temperature` and `max_temperature_celsius`.  Both kinds of sensors have the strings
+/// `chip`, `label` and `value` and the bool `critical`.  Temperatures also have the floats
+/// `celsius` and `critical_celsius`, and fans have the float `rpm`.
+fn publish_sensors(
+    ui: &Uis,
+    sensors: &[Sensor],
+    format: &FormatConfig,
+) -> Result<(), SetPropertyError> {
+    let mut temperatures = Vec::new();
+    let mut fans = Vec::new();
+    for sensor in sensors {
+        let mut fields = vec![
+            ("chip", Value::String(sensor.chip.as_str().into())),
+            ("label", Value::String(sensor.label.as_str().into())),
+            ("critical", Value::Bool(sensor.critical)),
+        ];
+        let list = match sensor.kind {
+            sensors::Kind::Temperature => {
+                fields.extend([
+                    (
+                        "value",
+                        Value::String(format.temperature(sensor.value).into()),
+                    ),
+                    ("celsius", sensor.value.into()),
+                    (
+                        "critical_celsius",
+                        sensor.critical_value.unwrap_or_default().into(),
+                    ),
+                ]);
+                &mut temperatures
+            }
+            sensors::Kind::Fan => {
+                fields.extend([
+                    (
+                        "value",
+                        Value::String(format!("{:.0}RPM", sensor.value).into()),
+                    ),
+                    ("rpm", sensor.value.into()),
+                ]);
+                &mut fans
+            }
+        };
+        list.push(struct_value(fields));
+    }
+    let max_temperature = sensors
+        .iter()
+        .filter(|s| s.kind == sensors::Kind::Temperature)
+        .map(|s| s.value)
+        .fold(0.0, f64::max);
+    let set = |name: &str, value: Value| ui.set_global_property("Sensors", name, &value);
+    set("temperatures", model(temperatures))?;
+    set("fans", model(fans))?;
+    set("critical", Value::Bool(sensors.iter().any(|s| s.critical)))?;
+    set(
+        "max_temperature",
+        Value::String(format.temperature(max_temperature).into()),
+    )?;
+    set("max_temperature_celsius", max_temperature.into())
+}
+
+/// Publish `history` to `HardwareMonitor` as a `[float]` called `<name>_history`, and as the
+/// commands of a path with a 100 by 100 viewbox, reaching the top at `max`, called
+/// `<name>_history_path`
+fn set_history(ui: &Uis, name: &str, history: &History, max: f32) -> Result<(), SetPropertyError> {
+    let values = history
+        .values()
+        .map(|value| Value::Number(value.into()))
+        .collect::<Vec<_>>();
+    set_hw(ui, &format!("{name}_history"), model(values))?;
+    set_hw(
+        ui,
+        &format!("{name}_history_path"),
+        Value::String(history.path_commands(max).into()),
+    )
+}
+
+/// A model of every logical cpu, for the `cores` property of `HardwareMonitor`.  Each cpu has
+/// `id` as an int, the strings `usage` and `frequency`, and the floats `usage_percent` and
+/// `frequency_mhz`.
+#[allow(clippy::cast_precision_loss)]
+fn cores_value(hw_mon: &hardware_mon::HardwareMonitor, format: &FormatConfig) -> Value {
+    let cores = hw_mon
+        .cores()
+        .map(|core| {
+            let frequency = core.frequency as f64;
+            struct_value([
+                ("id", Value::Number(core.id as f64)),
+                (
+                    "usage",
+                    Value::String(format.percent(core.usage.into()).into()),
+                ),
+                ("usage_percent", core.usage.into()),
+                (
+                    "frequency",
+                    Value::String(format.frequency(frequency).into()),
+                ),
+                ("frequency_mhz", frequency.into()),
+            ])
+        })
+        .collect::<Vec<Value>>();
+    model(cores)
+}
+
+/// A model of every network interface, for the `networks` property of `HardwareMonitor`.  Each
+/// interface has the strings `name`, `up`, `down`, `total_up`, `total_down` and `state`, the
+/// same in bytes as the floats `up_bytes`, `down_bytes`, `total_up_bytes` and
+/// `total_down_bytes`, and `addresses` as a `[string]`.
+#[allow(clippy::cast_precision_loss)]
+fn interfaces_value(
+    interfaces: &[hardware_mon::network::Interface],
+    format: &FormatConfig,
+) -> Value {
+    let interfaces = interfaces
+        .iter()
+        .map(|interface| {
+            let addresses = interface
+                .addresses
+                .iter()
+                .map(|address| Value::String(address.to_string().into()))
+                .collect::<Vec<_>>();
+            let (total_up, total_down) = (interface.total_up as f64, interface.total_down as f64);
+            struct_value([
+                ("name", Value::String(interface.name.as_str().into())),
+                ("up", Value::String(format.rate(interface.up).into())),
+                ("up_bytes", interface.up.into()),
+                ("down", Value::String(format.rate(interface.down).into())),
+                ("down_bytes", interface.down.into()),
+                ("total_up", Value::String(format.bytes(total_up).into())),
+                ("total_up_bytes", total_up.into()),
+                ("total_down", Value::String(format.bytes(total_down).into())),
+                ("total_down_bytes", total_down.into()),
+                ("state", Value::String(interface.state.as_str().into())),
+                ("addresses", model(addresses)),
+            ])
+        })
+        .collect::<Vec<Value>>();
+    model(interfaces)
+}
+
+/// A model of the selected filesystems, for the `disks` property of `HardwareMonitor`.  Each
+/// filesystem has the strings `mount_point`, `device`, `file_system`, `total`, `used`, `free`
+/// and `usage`, the sizes in bytes as the floats `total_bytes`, `used_bytes` and `free_bytes`,
+/// and the float `usage_percent`.
+#[allow(clippy::cast_precision_loss)]
+fn disks_value(disks: &[hardware_mon::disks::Disk], format: &FormatConfig) -> Value {
+    let disks = disks
+        .iter()
+        .map(|disk| {
+            let (total, used, free) = (disk.total as f64, disk.used as f64, disk.free as f64);
+            struct_value([
+                (
+                    "mount_point",
+                    Value::String(disk.mount_point.as_str().into()),
+                ),
+                ("device", Value::String(disk.device.as_str().into())),
+                (
+                    "file_system",
+                    Value::String(disk.file_system.as_str().into()),
+                ),
+                ("total", Value::String(format.bytes(total).into())),
+                ("total_bytes", total.into()),
+                ("used", Value::String(format.bytes(used).into())),
+                ("used_bytes", used.into()),
+                ("free", Value::String(format.bytes(free).into())),
+                ("free_bytes", free.into()),
+                ("usage", Value::String(format.percent(disk.usage()).into())),
+                ("usage_percent", disk.usage().into()),
+            ])
+        })
+        .collect::<Vec<Value>>();
+    model(disks)
+}
+
+/// A model of the selected block devices, for the `disk_io` property of `HardwareMonitor`.  Each
+/// device has the strings `name`, `read`, `write`, `total_read` and `total_written`, and the
+/// same in bytes as the floats `read_bytes`, `write_bytes`, `total_read_bytes` and
+/// `total_written_bytes`.
+#[allow(clippy::cast_precision_loss)]
+fn disk_io_value(devices: &[hardware_mon::disks::BlockDevice], format: &FormatConfig) -> Value {
+    let devices = devices
+        .iter()
+        .map(|device| {
+            let (total_read, total_written) =
+                (device.total_read as f64, device.total_written as f64);
+            struct_value([
+                ("name", Value::String(device.name.as_str().into())),
+                ("read", Value::String(format.rate(device.read).into())),
+                ("read_bytes", device.read.into()),
+                ("write", Value::String(format.rate(device.write).into())),
+                ("write_bytes", device.write.into()),
+                ("total_read", Value::String(format.bytes(total_read).into())),
+                ("total_read_bytes", total_read.into()),
+                (
+                    "total_written",
+                    Value::String(format.bytes(total_written).into()),
+                ),
+                ("total_written_bytes", total_written.into()),
+            ])
+        })
+        .collect::<Vec<Value>>();
+    model(devices)
+}
+
+/// Publish the status of the providers that changed to the `Providers` global
+fn publish_status(ui: &Uis, providers: &mut Providers) {
+    for (name, error) in providers.take_changed() {
+        let ok = ui.set_global_property(
+            "Providers",
+            &format!("{name}-ok"),
+            &Value::Bool(error.is_none()),
+        );
+        let error = ui.set_global_property(
+            "Providers",
+            &format!("{name}-error"),
+            &Value::String(error.unwrap_or_default().into()),
+        );
+        if let Err(e) = ok.and(error) {
+            tracing::warn!("failed to publish the status of '{name}': {e}");
+        }
+    }
+}
+
 #[cfg(feature = "workspaces")]
 pub mod hyprland {
     use embargo_workspace::WorkspaceState;
@@ -0,0 +1,193 @@
+use std::{
+    collections::HashMap,
+    os::unix::process::CommandExt,
+    process::{Command, Stdio},
+    sync::{mpsc, Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    config::{timings::Refresh, Script},
+    event_loop::Waker,
+};
+
+/// Runs the scripts from the config in the background.  The trimmed output of a script is
+/// published to the property named after it.
+pub struct Scripts {
+    entries: Vec<Entry>,
+    sender: mpsc::Sender<(usize, anyhow::Result<String>)>,
+    results: mpsc::Receiver<(usize, anyhow::Result<String>)>,
+    /// process ids of the scripts currently running
+    running: Arc<Mutex<Vec<u32>>>,
+    waker: Waker,
+}
+
+struct Entry {
+    name: String,
+    script: Script,
+    /// `None` once a script that never refreshes ran
+    next_run: Option<Instant>,
+    running: bool,
+}
+
+impl Scripts {
+    /// Every script runs right away
+    pub fn new(scripts: HashMap<String, Script>, waker: Waker) -> Self {
+        let (sender, results) = mpsc::channel();
+        let now = Instant::now();
+        Self {
+            entries: scripts
+                .into_iter()
+                .map(|(name, script)| Entry {
+                    name,
+                    script,
+                    next_run: Some(now),
+                    running: false,
+                })
+                .collect(),
+            sender,
+            results,
+            running: Arc::default(),
+            waker,
+        }
+    }
+    /// Run the script called `name`, or all of them, as soon as possible
+    pub fn refresh(&mut self, name: Option<&str>) -> anyhow::Result<()> {
+        let now = Instant::now();
+        let mut found = false;
+        for entry in &mut self.entries {
+            if name.is_none_or(|name| name == entry.name) {
+                entry.next_run = Some(now);
+                found = true;
+            }
+        }
+        match name {
+            Some(name) if !found => anyhow::bail!("there is no script called '{name}'"),
+            _ => {
+                self.waker.wake();
+                Ok(())
+            }
+        }
+    }
+    /// Start the scripts that are due and return the output of the ones that finished as
+    /// `(name, output)`
+    pub fn poll(&mut self) -> Vec<(String, anyhow::Result<String>)> {
+        let mut outputs = Vec::new();
+        while let Ok((index, result)) = self.results.try_recv() {
+            let entry = &mut self.entries[index];
+            entry.running = false;
+            if let Refresh::Continous(interval) = entry.script.update {
+                entry.next_run = entry.next_run.or(Some(Instant::now() + interval));
+            }
+            outputs.push((entry.name.clone(), result));
+        }
+        let now = Instant::now();
+        for (index, entry) in self.entries.iter_mut().enumerate() {
+            if entry.running || entry.next_run.is_none_or(|t| t > now) {
+                continue;
+            }
+            entry.running = true;
+            entry.next_run = None;
+            let script = entry.script.script.clone();
+            let sender = self.sender.clone();
+            let running = self.running.clone();
+            let waker = self.waker.clone();
+            std::thread::spawn(move || {
+                // the receiver is gone after a reload, which is fine
+                let _ = sender.send((index, run(&script, &running)));
+                waker.wake();
+            });
+        }
+        outputs
+    }
+    /// How long until the next script is due
+    pub fn timeout(&self) -> Option<Duration> {
+        let now = Instant::now();
+        self.entries
+            .iter()
+            .filter(|e| !e.running)
+            .filter_map(|e| e.next_run)
+            .min()
+            .map(|t| t.saturating_duration_since(now))
+    }
+}
+
+impl Drop for Scripts {
+    /// Terminate the scripts still running, along with anything they started
+    fn drop(&mut self) {
+        for &pid in self.running.lock().unwrap().iter() {
+            let Ok(pid) = libc::pid_t::try_from(pid) else {
+                continue;
+            };
+            // SAFETY: kill has no memory safety requirements
+            unsafe { libc::kill(-pid, libc::SIGTERM) };
+        }
+    }
+}
+
+fn run(script: &str, running: &Mutex<Vec<u32>>) -> anyhow::Result<String> {
+    let child = Command::new("sh")
+        .arg("-c")
+        .arg(script)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        // in its own process group, so it can be terminated together with its children
+        .process_group(0)
+        .spawn()?;
+    let pid = child.id();
+    running.lock().unwrap().push(pid);
+    let output = child.wait_with_output();
+    running.lock().unwrap().retain(|&p| p != pid);
+    let output = output?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "exited with {}: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event_loop::WakeReceiver;
+
+    #[test]
+    fn drop_terminates_the_running_scripts() {
+        let (waker, _receiver) = WakeReceiver::new().unwrap();
+        let mut scripts = Scripts::new(
+            HashMap::from([(
+                "slow".to_owned(),
+                Script {
+                    update: Refresh::Never,
+                    script: "sleep 60".to_owned(),
+                },
+            )]),
+            waker,
+        );
+        scripts.poll();
+        let started = Instant::now();
+        let pid = loop {
+            if let Some(&pid) = scripts.running.lock().unwrap().first() {
+                break libc::pid_t::try_from(pid).unwrap();
+            }
+            assert!(
+                started.elapsed() < Duration::from_secs(5),
+                "the script never started"
+            );
+            std::thread::sleep(Duration::from_millis(10));
+        };
+        drop(scripts);
+        // signal 0 only checks whether anything in the group is left
+        // SAFETY: kill has no memory safety requirements
+        while unsafe { libc::kill(-pid, 0) } == 0 {
+            assert!(
+                started.elapsed() < Duration::from_secs(5),
+                "the script is still running"
+            );
+            std::thread::sleep(Duration::from_millis(10));
+        }
+    }
+}
@@ -0,0 +1,64 @@
+use std::{
+    os::fd::AsRawFd,
+    sync::atomic::{AtomicBool, AtomicI32, Ordering},
+};
+
+use crate::event_loop::Waker;
+
+/// Written to by the handler to wake the main loop
+static WAKE_FD: AtomicI32 = AtomicI32::new(-1);
+/// The signal that asked us to exit, 0 if none did
+static EXIT: AtomicI32 = AtomicI32::new(0);
+static RELOAD: AtomicBool = AtomicBool::new(false);
+
+/// Exit cleanly on SIGTERM, SIGINT and SIGHUP, and reload the config on SIGUSR1.  Only the first
+/// exit signal is handled.  `waker` is woken when a signal arrives and has to stay alive for as
+/// long as the handlers are installed.
+pub fn install(waker: &Waker) -> std::io::Result<()> {
+    WAKE_FD.store(waker.as_raw_fd(), Ordering::Relaxed);
+    for signal in [libc::SIGTERM, libc::SIGINT, libc::SIGHUP, libc::SIGUSR1] {
+        // SAFETY: the handler only touches atomics and calls write, which is async-signal-safe
+        unsafe {
+            let mut action: libc::sigaction = std::mem::zeroed();
+            action.sa_sigaction = handle as *const () as libc::sighandler_t;
+            action.sa_flags = libc::SA_RESTART;
+            if signal != libc::SIGUSR1 {
+                // a second one exits right away, should the clean exit hang
+                action.sa_flags |= libc::SA_RESETHAND;
+            }
+            libc::sigemptyset(&raw mut action.sa_mask);
+            if libc::sigaction(signal, &raw const action, std::ptr::null_mut()) != 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+        }
+    }
+    Ok(())
+}
+
+extern "C" fn handle(signal: libc::c_int) {
+    if signal == libc::SIGUSR1 {
+        RELOAD.store(true, Ordering::Relaxed);
+    } else {
+        EXIT.store(signal, Ordering::Relaxed);
+    }
+    let fd = WAKE_FD.load(Ordering::Relaxed);
+    if fd >= 0 {
+        // SAFETY: writing one byte from a valid buffer
+        unsafe { libc::write(fd, [0u8].as_ptr().cast(), 1) };
+    }
+}
+
+/// The name of the signal that asked us to exit, if one did
+pub fn exit_requested() -> Option<&'static str> {
+    match EXIT.load(Ordering::Relaxed) {
+        0 => None,
+        libc::SIGTERM => Some("SIGTERM"),
+        libc::SIGINT => Some("SIGINT"),
+        _ => Some("SIGHUP"),
+    }
+}
+
+/// Whether SIGUSR1 arrived since the last call
+pub fn take_reload() -> bool {
+    RELOAD.swap(false, Ordering::Relaxed)
+}
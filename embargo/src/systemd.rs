@@ -0,0 +1,142 @@
+//! The parts of the systemd service protocol embargo speaks: readiness and watchdog
+//! notifications over `$NOTIFY_SOCKET`, and socket activation of the control socket.  Everything
+//! does nothing when not started by systemd.
+use std::{
+    os::{
+        fd::FromRawFd,
+        linux::net::SocketAddrExt,
+        unix::net::{SocketAddr, UnixDatagram, UnixListener},
+    },
+    path::Path,
+    time::{Duration, Instant},
+};
+
+/// The first fd passed by socket activation
+const LISTEN_FDS_START: i32 = 3;
+
+/// Send a state like `READY=1` to the service manager, if there is one
+pub fn notify(state: &str) {
+    let Some(path) = std::env::var_os("NOTIFY_SOCKET") else {
+        return;
+    };
+    if let Err(e) = notify_to(Path::new(&path), state) {
+        tracing::warn!("failed to notify systemd: {e}");
+    }
+}
+
+fn notify_to(path: &Path, state: &str) -> std::io::Result<()> {
+    let socket = UnixDatagram::unbound()?;
+    // a leading `@` names a socket in the abstract namespace
+    let address = match path.to_str().and_then(|p| p.strip_prefix('@')) {
+        Some(name) => SocketAddr::from_abstract_name(name)?,
+        None => SocketAddr::from_pathname(path)?,
+    };
+    socket.send_to_addr(state.as_bytes(), &address)?;
+    Ok(())
+}
+
+/// Whether a variable meant for a specific process, like `LISTEN_PID`, is meant for this one
+fn for_this_process(pid_variable: &str) -> bool {
+    std::env::var(pid_variable)
+        .ok()
+        .and_then(|pid| pid.parse::<u32>().ok())
+        .is_some_and(|pid| pid == std::process::id())
+}
+
+/// Pings the service manager often enough to not be considered hung, if the service has
+/// `WatchdogSec=` set
+pub struct Watchdog {
+    interval: Duration,
+    last_ping: Instant,
+}
+
+impl Watchdog {
+    pub fn from_env() -> Option<Self> {
+        if std::env::var_os("WATCHDOG_PID").is_some() && !for_this_process("WATCHDOG_PID") {
+            return None;
+        }
+        Self::new(&std::env::var("WATCHDOG_USEC").ok()?)
+    }
+    /// Ping at half the timeout systemd uses, given in microseconds
+    fn new(usec: &str) -> Option<Self> {
+        let usec = usec.parse::<u64>().ok().filter(|&usec| usec > 0)?;
+        Some(Self {
+            interval: Duration::from_micros(usec) / 2,
+            last_ping: Instant::now(),
+        })
+    }
+    /// Send `WATCHDOG=1` if it's time to
+    pub fn ping_if_due(&mut self) {
+        if self.last_ping.elapsed() >= self.interval {
+            notify("WATCHDOG=1");
+            self.last_ping = Instant::now();
+        }
+    }
+    /// How long until the next ping is due
+    pub fn timeout(&self) -> Duration {
+        self.interval.saturating_sub(self.last_ping.elapsed())
+    }
+}
+
+/// The control socket, if systemd opened it for us.  The socket unit should listen on the path
+/// `embargo msg` connects to, e.g. `ListenStream=%t/embargo.sock`.
+pub fn activated_listener() -> Option<UnixListener> {
+    if !for_this_process("LISTEN_PID") {
+        return None;
+    }
+    let fds = std::env::var("LISTEN_FDS").ok()?.parse::<i32>().ok()?;
+    // scripts started later must not think they were activated
+    for variable in ["LISTEN_PID", "LISTEN_FDS", "LISTEN_FDNAMES"] {
+        std::env::remove_var(variable);
+    }
+    if fds < 1 {
+        return None;
+    }
+    if fds > 1 {
+        tracing::warn!("only the first of {fds} activated sockets is used");
+    }
+    // SAFETY: systemd passes the sockets starting at fd 3 and nothing else owns them
+    unsafe {
+        libc::fcntl(LISTEN_FDS_START, libc::F_SETFD, libc::FD_CLOEXEC);
+        Some(UnixListener::from_raw_fd(LISTEN_FDS_START))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::temp_dir::TempDir;
+
+    #[test]
+    fn notify_socket() {
+        let dir = TempDir::new("notify");
+        let path = dir.join("notify");
+        let receiver = UnixDatagram::bind(&path).unwrap();
+
+        notify_to(&path, "READY=1").unwrap();
+        let mut buf = [0; 64];
+        let len = receiver.recv(&mut buf).unwrap();
+        assert_eq!(&buf[..len], b"READY=1");
+    }
+
+    #[test]
+    fn abstract_notify_socket() {
+        let name = format!("embargo-notify-{}", std::process::id());
+        let receiver =
+            UnixDatagram::bind_addr(&SocketAddr::from_abstract_name(&name).unwrap()).unwrap();
+
+        notify_to(Path::new(&format!("@{name}")), "WATCHDOG=1").unwrap();
+        let mut buf = [0; 64];
+        let len = receiver.recv(&mut buf).unwrap();
+        assert_eq!(&buf[..len], b"WATCHDOG=1");
+    }
+
+    #[test]
+    fn watchdog_interval() {
+        let watchdog = Watchdog::new("10000000").unwrap();
+        assert_eq!(watchdog.interval, Duration::from_secs(5));
+        assert!(watchdog.timeout() <= Duration::from_secs(5));
+        assert!(Watchdog::new("0").is_none());
+        assert!(Watchdog::new("soon").is_none());
+    }
+}
@@ -0,0 +1,50 @@
+//! Directories of files for the tests, e.g. stand-ins for parts of sysfs and procfs
+use std::{
+    ops::Deref,
+    path::{Path, PathBuf},
+};
+
+/// A directory in the temp dir that is removed again on drop
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    /// An empty directory named after `test`, so tests running at the same time don't share one
+    pub fn new(test: &str) -> Self {
+        let path = std::env::temp_dir().join(format!("embargo-{test}-{}", std::process::id()));
+        // left behind by a run that panicked
+        let _ = std::fs::remove_dir_all(&path);
+        std::fs::create_dir_all(&path).unwrap();
+        Self(path)
+    }
+    /// A directory named after `test` holding `files`, see [`TempDir::write`]
+    pub fn with_files(
+        test: &str,
+        files: impl IntoIterator<Item = (impl AsRef<Path>, impl AsRef<[u8]>)>,
+    ) -> Self {
+        let dir = Self::new(test);
+        dir.write(files);
+        dir
+    }
+    /// Write `files` as `(path, contents)`, with paths relative to the directory.  Missing
+    /// parent directories are created and existing files are replaced.
+    pub fn write(&self, files: impl IntoIterator<Item = (impl AsRef<Path>, impl AsRef<[u8]>)>) {
+        for (path, contents) in files {
+            let path = self.0.join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, contents).unwrap();
+        }
+    }
+}
+
+impl Deref for TempDir {
+    type Target = Path;
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
@@ -5,6 +5,10 @@ use std::collections::HashMap;
 
 use embargo_workspace::WorkspaceState;
 pub fn workspaces() -> anyhow::Result<Vec<HyprlandWorkspace>> {
+    // the hyprland crate panics instead of returning an error when it can't find the socket
+    if std::env::var_os("HYPRLAND_INSTANCE_SIGNATURE").is_none() {
+        anyhow::bail!("Hyprland is not running");
+    }
     let workspaces = hyprland::data::Workspaces::get()?.to_vec();
     let mut workspace_icons = (1..10)
         .map(|id| (id as u32, WorkspaceState::Unused))
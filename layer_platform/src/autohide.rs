@@ -0,0 +1,59 @@
+use std::time::{Duration, Instant};
+
+/// Hide a bar until the pointer touches the edge of the screen.  While hidden the layer surface
+/// shrinks to a thin trigger strip that doesn't reserve any space.
+pub struct AutoHide {
+    /// Whether the bar currently hides itself.  Can be changed at runtime with
+    /// [`LayerWindow::set_auto_hide`](crate::LayerWindow::set_auto_hide).
+    pub enabled: bool,
+    /// How long to wait after the pointer left the bar before hiding it
+    pub delay: Duration,
+    /// Thickness of the strip that stays on screen while hidden
+    pub trigger_size: u32,
+    /// Time given to the slint content to animate out before the surface is collapsed
+    pub animation: Duration,
+    /// Called with `true` when the bar starts to reveal itself and `false` when it starts to hide,
+    /// so the slint content can animate
+    pub on_reveal: Option<Box<dyn Fn(bool)>>,
+}
+
+impl Default for AutoHide {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            delay: Duration::from_millis(500),
+            trigger_size: 2,
+            animation: Duration::ZERO,
+            on_reveal: None,
+        }
+    }
+}
+
+/// Where a single bar instance is in the hide/reveal cycle
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Visibility {
+    Shown,
+    /// the pointer left at the contained time
+    Leaving(Instant),
+    /// the content is animating out and the surface collapses at the contained time
+    Hiding(Instant),
+    Hidden,
+}
+
+impl Visibility {
+    /// Whether the slint content should be revealed
+    pub(crate) fn revealed(self) -> bool {
+        matches!(self, Self::Shown | Self::Leaving(_))
+    }
+    /// Advance timers.  Returns the new state if it changed.
+    pub(crate) fn tick(self, auto_hide: &AutoHide) -> Option<Self> {
+        let now = Instant::now();
+        match self {
+            Self::Leaving(left) if now.duration_since(left) >= auto_hide.delay => {
+                Some(Self::Hiding(now + auto_hide.animation))
+            }
+            Self::Hiding(collapse) if now >= collapse => Some(Self::Hidden),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,106 @@
+use smithay_client_toolkit::output::OutputInfo;
+
+/// Decides which outputs get a bar.  Patterns are globs (`*` and `?`) matched against both the
+/// name (`DP-1`) and the description of an output.
+#[derive(Debug, Clone, Default)]
+pub struct OutputFilter {
+    /// Only outputs matching one of these patterns get a bar.  Empty means every output.
+    pub include: Vec<String>,
+    /// Outputs matching any of these patterns never get a bar
+    pub exclude: Vec<String>,
+    /// Only put a bar on the first matching output the compositor advertised
+    pub primary_only: bool,
+}
+
+impl OutputFilter {
+    /// Whether the filter needs output info to make a decision
+    pub fn needs_info(&self) -> bool {
+        !self.include.is_empty() || !self.exclude.is_empty()
+    }
+    /// Check an output against the include and exclude patterns.  Outputs we don't know anything
+    /// about yet only pass if there is nothing to match against.
+    pub fn matches(&self, info: Option<&OutputInfo>) -> bool {
+        let Some(info) = info else {
+            return !self.needs_info();
+        };
+        let names = [info.name.as_deref(), info.description.as_deref()];
+        let matches_any = |patterns: &[String]| {
+            patterns
+                .iter()
+                .any(|p| names.iter().flatten().any(|name| glob_match(p, name)))
+        };
+        (self.include.is_empty() || matches_any(&self.include)) && !matches_any(&self.exclude)
+    }
+}
+
+/// Match `text` against a glob `pattern` where `*` matches any run of characters and `?` matches
+/// exactly one
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern = pattern.chars().collect::<Vec<_>>();
+    let text = text.chars().collect::<Vec<_>>();
+    let (mut p, mut t) = (0, 0);
+    // position of the last `*` in the pattern and the text position it was tried at
+    let mut backtrack = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                Some((star, star_t)) => {
+                    p = star + 1;
+                    t = star_t + 1;
+                    backtrack = Some((star, star_t + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn star() {
+        assert!(glob_match("DP-*", "DP-1"));
+        assert!(glob_match("DP-*", "DP-"));
+        assert!(glob_match("*-1", "HDMI-A-1"));
+        assert!(glob_match("*A*1", "HDMI-A-1"));
+        assert!(glob_match("**", ""));
+        // the first `*` has to give back what the rest of the pattern needs
+        assert!(glob_match("*-A-1", "HDMI-A-A-1"));
+        assert!(!glob_match("DP-*", "HDMI-A-1"));
+    }
+
+    #[test]
+    fn question_mark() {
+        assert!(glob_match("DP-?", "DP-1"));
+        assert!(glob_match("??", "é1"));
+        assert!(!glob_match("DP-?", "DP-"));
+        assert!(!glob_match("DP-?", "DP-10"));
+    }
+
+    #[test]
+    fn anchored() {
+        assert!(glob_match("DP-1", "DP-1"));
+        assert!(!glob_match("DP", "DP-1"));
+        assert!(!glob_match("P-1", "DP-1"));
+        assert!(!glob_match("dp-1", "DP-1"));
+    }
+
+    #[test]
+    fn empty() {
+        assert!(glob_match("", ""));
+        assert!(glob_match("*", ""));
+        assert!(!glob_match("", "DP-1"));
+        assert!(!glob_match("?", ""));
+    }
+}
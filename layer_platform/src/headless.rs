@@ -0,0 +1,103 @@
+use std::{
+    io::{Read, Write},
+    path::Path,
+    rc::Rc,
+};
+
+use slint::{platform::software_renderer::MinimalSoftwareWindow, PhysicalSize};
+
+use crate::ui::RgbaPixel;
+
+/// Renders a slint window into memory instead of onto a layer surface.  Uses the same software
+/// renderer as [`Bar`](crate::Bar), so the result matches what ends up on screen.
+pub struct Headless {
+    window: Rc<MinimalSoftwareWindow>,
+    width: u32,
+    height: u32,
+    start_pixel: RgbaPixel,
+    pub software_buffer: Vec<RgbaPixel>,
+}
+
+impl Headless {
+    pub fn new(
+        window: Rc<MinimalSoftwareWindow>,
+        start_pixel: RgbaPixel,
+        width: u32,
+        height: u32,
+    ) -> Self {
+        window.set_size(PhysicalSize::new(width, height));
+        Self {
+            window,
+            width,
+            height,
+            start_pixel,
+            software_buffer: vec![start_pixel; (width * height) as usize],
+        }
+    }
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+    /// Render a full frame into the buffer
+    pub fn render(&mut self) -> &[RgbaPixel] {
+        slint::platform::update_timers_and_animations();
+        self.software_buffer.fill(self.start_pixel);
+        self.window.request_redraw();
+        self.window.draw_if_needed(|renderer| {
+            renderer.render(&mut self.software_buffer, self.width as usize);
+        });
+        &self.software_buffer
+    }
+    /// Encode the buffer as an RGBA png
+    pub fn write_png(&self, writer: impl Write) -> anyhow::Result<()> {
+        write_png(writer, &self.software_buffer, self.width, self.height)
+    }
+    pub fn save_png(&self, path: &Path) -> anyhow::Result<()> {
+        self.write_png(std::io::BufWriter::new(std::fs::File::create(path)?))
+    }
+}
+
+/// Encode premultiplied pixels as a straight alpha RGBA png
+pub fn write_png(
+    writer: impl Write,
+    pixels: &[RgbaPixel],
+    width: u32,
+    height: u32,
+) -> anyhow::Result<()> {
+    let mut encoder = png::Encoder::new(writer, width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    let data = pixels
+        .iter()
+        .flat_map(|p| {
+            let unpremultiply = |c: u8| match p.alpha {
+                0 => 0,
+                u8::MAX => c,
+                a => (u16::from(c) * 255 / u16::from(a)).min(255) as u8,
+            };
+            [
+                unpremultiply(p.red),
+                unpremultiply(p.green),
+                unpremultiply(p.blue),
+                p.alpha,
+            ]
+        })
+        .collect::<Vec<_>>();
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&data)?;
+    Ok(())
+}
+
+/// Decode an 8 bit RGBA png into its size and straight alpha RGBA bytes
+pub fn read_png(reader: impl Read) -> anyhow::Result<(u32, u32, Vec<u8>)> {
+    let mut reader = png::Decoder::new(reader).read_info()?;
+    let mut data = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut data)?;
+    if info.color_type != png::ColorType::Rgba || info.bit_depth != png::BitDepth::Eight {
+        anyhow::bail!("expected an 8 bit RGBA png");
+    }
+    data.truncate(info.buffer_size());
+    Ok((info.width, info.height, data))
+}
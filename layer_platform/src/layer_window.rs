@@ -0,0 +1,231 @@
+use std::{cell::RefCell, rc::Rc};
+
+use slint::platform::software_renderer::MinimalSoftwareWindow;
+use smithay_client_toolkit::shell::wlr_layer::{Anchor, KeyboardInteractivity, Layer};
+
+use crate::{AutoHide, Bar, InputRegion, OutputFilter, RgbaPixel};
+
+/// Describes a slint window shown on layer surfaces, such as a bar, an OSD or a desktop widget.
+/// [`build`](Self::build) adds it to a [`Bar`] and returns a [`LayerWindow`] to control it.
+pub struct LayerWindowBuilder {
+    state: WindowState,
+}
+
+/// How much space a layer window reserves at the edge it is anchored to
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ExclusiveZone {
+    /// Don't reserve any space
+    #[default]
+    None,
+    /// Reserve the thickness of the window, like a bar does
+    Auto,
+    /// Reserve a fixed number of pixels.  -1 asks to not be moved by other exclusive zones.
+    Fixed(i32),
+}
+
+impl LayerWindowBuilder {
+    /// A window of `width`x`height` in the middle of every output, on the top layer
+    pub fn new(window: Rc<MinimalSoftwareWindow>, width: u32, height: u32) -> Self {
+        Self {
+            state: WindowState {
+                window,
+                namespace: "layer_platform".into(),
+                anchor: Anchor::empty(),
+                layer: Layer::Top,
+                width,
+                height,
+                span_output: false,
+                focused_output: false,
+                margin: [0; 4],
+                exclusive_zone: ExclusiveZone::None,
+                keyboard_interactivity: KeyboardInteractivity::None,
+                outputs: OutputFilter::default(),
+                start_pixel: RgbaPixel::transparent(),
+                auto_hide: None,
+                input_region: InputRegion::Full,
+                visible: true,
+                hidden_on: Vec::new(),
+                destroyed: false,
+                changed: false,
+                auto_hide_changed: false,
+                revealed: true,
+            },
+        }
+    }
+    /// The namespace of the layer surfaces, which compositors use to tell them apart in their rules
+    pub fn namespace(mut self, namespace: impl Into<String>) -> Self {
+        self.state.namespace = namespace.into();
+        self
+    }
+    /// The edges of the output to attach the window to.  Without any the window is centred.
+    pub fn anchor(mut self, anchor: Anchor) -> Self {
+        self.state.anchor = anchor;
+        self
+    }
+    pub fn layer(mut self, layer: Layer) -> Self {
+        self.state.layer = layer;
+        self
+    }
+    pub fn size(mut self, width: u32, height: u32) -> Self {
+        self.state.width = width;
+        self.state.height = height;
+        self
+    }
+    /// Stretch the window over the full width of each output, or the full height when it is
+    /// anchored to a side.  The size is then only used until the size of the output is known.
+    pub fn span_output(mut self, span_output: bool) -> Self {
+        self.state.span_output = span_output;
+        self
+    }
+    /// Leave the output up to the compositor, which usually picks the focused one, instead of
+    /// showing the window on every output the filter selects.  The output is picked again every
+    /// time the window is shown.
+    pub fn focused_output(mut self, focused_output: bool) -> Self {
+        self.state.focused_output = focused_output;
+        self
+    }
+    /// Distance to the anchored edges
+    pub fn margin(mut self, top: i32, right: i32, bottom: i32, left: i32) -> Self {
+        self.state.margin = [top, right, bottom, left];
+        self
+    }
+    pub fn exclusive_zone(mut self, exclusive_zone: ExclusiveZone) -> Self {
+        self.state.exclusive_zone = exclusive_zone;
+        self
+    }
+    pub fn keyboard_interactivity(mut self, keyboard_interactivity: KeyboardInteractivity) -> Self {
+        self.state.keyboard_interactivity = keyboard_interactivity;
+        self
+    }
+    /// Which outputs get an instance of the window
+    pub fn outputs(mut self, outputs: OutputFilter) -> Self {
+        self.state.outputs = outputs;
+        self
+    }
+    /// The colour the buffer is cleared to before every frame
+    pub fn start_pixel(mut self, start_pixel: RgbaPixel) -> Self {
+        self.state.start_pixel = start_pixel;
+        self
+    }
+    /// Hide the window until the pointer touches the edge it is anchored to
+    pub fn auto_hide(mut self, auto_hide: AutoHide) -> Self {
+        self.state.auto_hide = Some(auto_hide);
+        self
+    }
+    /// The part of the window that accepts pointer input
+    pub fn input_region(mut self, input_region: InputRegion) -> Self {
+        self.state.input_region = input_region;
+        self
+    }
+    /// Whether the window starts out shown
+    pub fn visible(mut self, visible: bool) -> Self {
+        self.state.visible = visible;
+        self
+    }
+    /// Add the window to `bar`.  Its surfaces are created the next time `bar` renders.
+    pub fn build(self, bar: &mut Bar) -> LayerWindow {
+        bar.add_window(self.state)
+    }
+}
+
+/// A slint window on layer surfaces.  Changes made through the handle are applied the next time
+/// the [`Bar`] it was added to renders.  Clones refer to the same window.
+#[derive(Clone)]
+pub struct LayerWindow(pub(crate) Rc<RefCell<WindowState>>);
+
+impl LayerWindow {
+    /// The slint window rendered onto the surfaces
+    pub fn window(&self) -> Rc<MinimalSoftwareWindow> {
+        self.0.borrow().window.clone()
+    }
+    pub fn namespace(&self) -> String {
+        self.0.borrow().namespace.clone()
+    }
+    pub fn show(&self) {
+        self.update(|state| state.visible = true);
+    }
+    pub fn hide(&self) {
+        self.update(|state| state.visible = false);
+    }
+    pub fn is_visible(&self) -> bool {
+        self.0.borrow().visible
+    }
+    /// Show the window on the output named `output` again after [`hide_on`](Self::hide_on)
+    pub fn show_on(&self, output: &str) {
+        self.update(|state| state.hidden_on.retain(|o| o != output));
+    }
+    /// Take the window off the output named `output`, e.g. `DP-1`, while leaving it on the others
+    pub fn hide_on(&self, output: &str) {
+        self.update(|state| {
+            if !state.hidden_on.iter().any(|o| o == output) {
+                state.hidden_on.push(output.to_owned());
+            }
+        });
+    }
+    /// Whether the window is shown at all and not hidden on `output`.  The output filter isn't
+    /// taken into account.
+    pub fn is_visible_on(&self, output: &str) -> bool {
+        let state = self.0.borrow();
+        state.visible && !state.hidden_on.iter().any(|o| o == output)
+    }
+    /// Change the size.  A window spanning its output only keeps the thickness.
+    pub fn set_size(&self, width: u32, height: u32) {
+        self.update(|state| {
+            state.width = width;
+            state.height = height;
+        });
+    }
+    /// Turn auto-hiding on or off.  Windows built without
+    /// [`auto_hide`](LayerWindowBuilder::auto_hide) hide with the [default](AutoHide::default)
+    /// timings.  Normal windows are always shown.
+    pub fn set_auto_hide(&self, enabled: bool) {
+        self.update(|state| {
+            if state.auto_hide.is_none() && !enabled {
+                return;
+            }
+            state
+                .auto_hide
+                .get_or_insert_with(AutoHide::default)
+                .enabled = enabled;
+            state.auto_hide_changed = true;
+        });
+    }
+    /// Remove the window and its surfaces for good
+    pub fn destroy(&self) {
+        self.update(|state| state.destroyed = true);
+    }
+    fn update(&self, f: impl FnOnce(&mut WindowState)) {
+        let mut state = self.0.borrow_mut();
+        f(&mut state);
+        state.changed = true;
+    }
+}
+
+/// Everything about a layer window, shared between its handles and the [`Bar`] showing it
+pub(crate) struct WindowState {
+    pub(crate) window: Rc<MinimalSoftwareWindow>,
+    pub(crate) namespace: String,
+    pub(crate) anchor: Anchor,
+    pub(crate) layer: Layer,
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+    pub(crate) span_output: bool,
+    pub(crate) focused_output: bool,
+    /// top, right, bottom, left
+    pub(crate) margin: [i32; 4],
+    pub(crate) exclusive_zone: ExclusiveZone,
+    pub(crate) keyboard_interactivity: KeyboardInteractivity,
+    pub(crate) outputs: OutputFilter,
+    pub(crate) start_pixel: RgbaPixel,
+    pub(crate) auto_hide: Option<AutoHide>,
+    pub(crate) input_region: InputRegion,
+    pub(crate) visible: bool,
+    /// names of the outputs the window was hidden on
+    pub(crate) hidden_on: Vec<String>,
+    pub(crate) destroyed: bool,
+    /// set by the handle when the [`Bar`] has to apply something
+    pub(crate) changed: bool,
+    pub(crate) auto_hide_changed: bool,
+    /// the last value passed to [`AutoHide::on_reveal`]
+    pub(crate) revealed: bool,
+}
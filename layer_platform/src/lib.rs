@@ -1,6 +1,16 @@
+mod autohide;
+mod filter;
+mod headless;
+mod layer_window;
+mod region;
 mod ui;
 mod window;
-pub use smithay_client_toolkit::shell::wlr_layer::Anchor;
-pub use ui::{LayerShellPlatform, RgbaPixel};
-pub use window::Bar;
+pub use autohide::AutoHide;
+pub use filter::{glob_match, OutputFilter};
+pub use headless::{read_png, write_png, Headless};
+pub use layer_window::{ExclusiveZone, LayerWindow, LayerWindowBuilder};
+pub use region::{InputRegion, Rect};
+pub use smithay_client_toolkit::shell::wlr_layer::{Anchor, KeyboardInteractivity, Layer};
+pub use ui::{LayerShellPlatform, RgbaPixel, WindowQueue};
+pub use window::{Bar, BarDefinition, SurfaceKind};
 pub type EventQueue = wayland_client::EventQueue<Bar>;
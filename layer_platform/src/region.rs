@@ -0,0 +1,141 @@
+use crate::ui::RgbaPixel;
+
+/// Which part of a bar accepts pointer input.  Input outside the region goes to whatever is
+/// underneath the bar.
+#[derive(Debug, Clone, Default)]
+pub enum InputRegion {
+    /// The whole surface
+    #[default]
+    Full,
+    /// Every pixel that isn't fully transparent, recomputed whenever the bar is redrawn
+    Opaque,
+    /// A fixed list of rectangles in surface coordinates
+    Rects(Vec<Rect>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rect {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+}
+
+impl InputRegion {
+    /// The rectangles making up the region for a rendered buffer.  `None` means the whole
+    /// surface.
+    pub(crate) fn rects(&self, buffer: &[RgbaPixel], width: u32) -> Option<Vec<Rect>> {
+        match self {
+            Self::Full => None,
+            Self::Opaque => Some(opaque_rects(buffer, width as usize)),
+            Self::Rects(rects) => Some(rects.clone()),
+        }
+    }
+}
+
+/// Cover the non-transparent pixels of `buffer` with rectangles.  Each row is split into runs of
+/// opaque pixels, and runs that line up with a run on the row above extend its rectangle
+/// downwards, so a bar made of a few boxes turns into a few rectangles.
+fn opaque_rects(buffer: &[RgbaPixel], width: usize) -> Vec<Rect> {
+    let mut finished = Vec::new();
+    // rectangles that reached the previous row
+    let mut open: Vec<Rect> = Vec::new();
+    for (y, row) in buffer.chunks(width.max(1)).enumerate() {
+        let mut runs = Vec::new();
+        let mut start = None;
+        for (x, pixel) in row.iter().chain([&RgbaPixel::transparent()]).enumerate() {
+            match (start, pixel.alpha > 0) {
+                (None, true) => start = Some(x),
+                (Some(s), false) => {
+                    runs.push((s as i32, (x - s) as i32));
+                    start = None;
+                }
+                _ => {}
+            }
+        }
+        let mut next = Vec::with_capacity(runs.len());
+        for (x, run_width) in runs {
+            match open.iter().position(|r| r.x == x && r.width == run_width) {
+                Some(index) => {
+                    let mut rect = open.swap_remove(index);
+                    rect.height += 1;
+                    next.push(rect);
+                }
+                None => next.push(Rect {
+                    x,
+                    y: y as i32,
+                    width: run_width,
+                    height: 1,
+                }),
+            }
+        }
+        finished.append(&mut open);
+        open = next;
+    }
+    finished.append(&mut open);
+    finished
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A buffer drawn with one string per row, `#` for opaque, `+` for barely visible and `.` for
+    /// transparent pixels
+    fn buffer(rows: &[&str]) -> (Vec<RgbaPixel>, usize) {
+        let pixels = rows
+            .iter()
+            .flat_map(|row| row.chars())
+            .map(|c| RgbaPixel {
+                alpha: match c {
+                    '#' => 255,
+                    '+' => 1,
+                    _ => 0,
+                },
+                ..RgbaPixel::transparent()
+            })
+            .collect();
+        (pixels, rows[0].len())
+    }
+
+    fn rects(rows: &[&str]) -> Vec<Rect> {
+        let (buffer, width) = buffer(rows);
+        let mut rects = opaque_rects(&buffer, width);
+        rects.sort_by_key(|r| (r.y, r.x));
+        rects
+    }
+
+    fn rect(x: i32, y: i32, width: i32, height: i32) -> Rect {
+        Rect {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    #[test]
+    fn transparent() {
+        assert_eq!(rects(&["....", "....", "...."]), []);
+    }
+
+    #[test]
+    fn opaque() {
+        assert_eq!(rects(&["####", "####", "####"]), [rect(0, 0, 4, 3)]);
+    }
+
+    #[test]
+    fn mixed_rows() {
+        assert_eq!(
+            rects(&["##..+#", "##..##", "######", "..##..", "......", "..##.."]),
+            [
+                rect(0, 0, 2, 2),
+                rect(4, 0, 2, 2),
+                rect(0, 2, 6, 1),
+                rect(2, 3, 2, 1),
+                // a gap ends the rectangle above it
+                rect(2, 5, 2, 1),
+            ]
+        );
+    }
+}
@@ -1,26 +1,54 @@
-use std::{rc::Rc, time::Instant};
+use std::{cell::RefCell, collections::VecDeque, rc::Rc, time::Instant};
 
 use slint::platform::{
     software_renderer::{MinimalSoftwareWindow, TargetPixel},
     Platform,
 };
+/// Hands out the given windows, one for each slint component created, in order
 pub struct LayerShellPlatform {
-    window: Rc<MinimalSoftwareWindow>,
+    windows: WindowQueue,
     start_time: Instant,
 }
 impl LayerShellPlatform {
-    pub fn new(window: Rc<MinimalSoftwareWindow>) -> Self {
+    pub fn new(windows: impl IntoIterator<Item = Rc<MinimalSoftwareWindow>>) -> Self {
+        let queue = WindowQueue::default();
+        for window in windows {
+            queue.push(window);
+        }
         Self {
-            window,
+            windows: queue,
             start_time: Instant::now(),
         }
     }
+    /// The windows not handed out yet.  More can be added through it after the platform was
+    /// set, e.g. to create components for a reloaded config.
+    pub fn windows(&self) -> WindowQueue {
+        self.windows.clone()
+    }
+}
+
+/// The windows a [`LayerShellPlatform`] hands out next
+#[derive(Clone, Default)]
+pub struct WindowQueue(Rc<RefCell<VecDeque<Rc<MinimalSoftwareWindow>>>>);
+impl WindowQueue {
+    pub fn push(&self, window: Rc<MinimalSoftwareWindow>) {
+        self.0.borrow_mut().push_back(window);
+    }
+    /// Drop the windows not handed out yet, e.g. after a component failed to load
+    pub fn clear(&self) {
+        self.0.borrow_mut().clear();
+    }
 }
 impl Platform for LayerShellPlatform {
     fn create_window_adapter(
         &self,
     ) -> Result<std::rc::Rc<dyn slint::platform::WindowAdapter>, slint::PlatformError> {
-        Ok(self.window.clone())
+        match self.windows.0.borrow_mut().pop_front() {
+            Some(window) => Ok(window),
+            None => Err(slint::PlatformError::Other(
+                "more components were created than windows were given to the platform".into(),
+            )),
+        }
     }
     fn duration_since_start(&self) -> core::time::Duration {
         self.start_time.elapsed()
@@ -59,6 +87,7 @@ impl TargetPixel for RgbaPixel {
         self.red = (self.red as u16 * alpha / 255) as u8 + color.red;
         self.green = (self.green as u16 * alpha / 255) as u8 + color.green;
         self.blue = (self.blue as u16 * alpha / 255) as u8 + color.blue;
+        self.alpha = (self.alpha as u16 * alpha / 255) as u8 + color.alpha;
     }
 }
 impl Default for RgbaPixel {
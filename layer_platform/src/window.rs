@@ -1,17 +1,25 @@
-use std::rc::Rc;
+use std::{
+    cell::{Ref, RefCell},
+    rc::Rc,
+    time::Instant,
+};
 use tracing::{event, Level};
 
+use crate::autohide::{AutoHide, Visibility};
+use crate::filter::OutputFilter;
+use crate::layer_window::{ExclusiveZone, LayerWindow, LayerWindowBuilder, WindowState};
+use crate::region::{InputRegion, Rect};
 use crate::ui::RgbaPixel;
 use crate::EventQueue;
 use slint::{
     platform::{software_renderer::MinimalSoftwareWindow, PointerEventButton},
-    LogicalPosition,
+    LogicalPosition, PhysicalSize,
 };
 use smithay_client_toolkit::{
-    compositor::{CompositorHandler, CompositorState},
+    compositor::{CompositorHandler, CompositorState, Region},
     delegate_compositor, delegate_layer, delegate_output, delegate_pointer, delegate_registry,
-    delegate_seat, delegate_shm,
-    output::{OutputHandler, OutputState},
+    delegate_seat, delegate_shm, delegate_xdg_shell, delegate_xdg_window,
+    output::{OutputHandler, OutputInfo, OutputState},
     registry::{ProvidesRegistryState, RegistryState},
     registry_handlers,
     seat::{
@@ -22,153 +30,702 @@ use smithay_client_toolkit::{
         wlr_layer::{
             Anchor, Layer, LayerShell, LayerShellHandler, LayerSurface, LayerSurfaceConfigure,
         },
+        xdg::{
+            window::{Window, WindowConfigure, WindowDecorations, WindowHandler},
+            XdgShell,
+        },
         WaylandSurface,
     },
     shm::{slot::SlotPool, Shm, ShmHandler},
 };
 use wayland_client::{
     globals::{registry_queue_init, GlobalList},
-    protocol::{wl_output, wl_pointer, wl_seat, wl_shm, wl_surface},
-    Connection, QueueHandle,
+    protocol::{
+        wl_output::{self, Transform},
+        wl_pointer, wl_seat, wl_shm, wl_surface,
+    },
+    Connection, Proxy, QueueHandle,
 };
+/// Owns the connection to the compositor and shows [`LayerWindow`]s on it.  The bars passed to
+/// [`Bar::new`] are layer windows like any other.
 pub struct Bar {
     config: BarConfig,
     pointer: Option<wl_pointer::WlPointer>,
-    window: Rc<MinimalSoftwareWindow>,
-    pub software_buffer: Vec<RgbaPixel>,
+    windows: Vec<LayerWindow>,
     pool: SlotPool,
     registry_state: RegistryState,
     seat_state: SeatState,
-    layer_name: String,
     shm: Shm,
     output_state: OutputState,
     instances: Vec<BarInstance>,
     compositor: CompositorState,
     pub exit: bool,
-    layer_shell: LayerShell,
+    shell: Shell,
+}
+
+/// How bars are put on screen
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SurfaceKind {
+    /// A layer surface on every output the bar's filter selects
+    #[default]
+    Layer,
+    /// One normal, resizable window per bar.  Meant for working on the design of a bar, also on
+    /// compositors without layer-shell.  Auto-hiding is disabled.
+    Windowed,
+}
+
+enum Shell {
+    Layer(LayerShell),
+    Xdg(XdgShell),
 }
 impl Bar {
-    pub fn new(
-        window: Rc<MinimalSoftwareWindow>,
-        start_pixel: RgbaPixel,
-        position: Anchor,
-        layer_name: &str,
-        width: u32,
-        height: u32,
+    /// Connect to the compositor and show every bar in `bars` on the outputs it selects.  All
+    /// bars share one connection and event queue.
+    pub fn new(bars: Vec<BarDefinition>, kind: SurfaceKind) -> anyhow::Result<(Self, EventQueue)> {
+        Self::with_connection(&Connection::connect_to_env()?, bars, kind)
+    }
+    /// Like [`Bar::new`], but on an existing connection instead of the one named by
+    /// `WAYLAND_DISPLAY`
+    pub fn with_connection(
+        conn: &Connection,
+        bars: Vec<BarDefinition>,
+        kind: SurfaceKind,
     ) -> anyhow::Result<(Self, EventQueue)> {
-        let conn = Connection::connect_to_env()?;
-        let (config, event_queue) = BarConfig::new(&conn, position, width, height)?;
+        let (config, event_queue) = BarConfig::new(conn)?;
         let shm = Shm::bind(&config.globals, &config.qh).expect("wl_shm is not available");
-        let pool = SlotPool::new((config.width * config.height * 4) as usize, &shm)?;
-        let layer_shell = LayerShell::bind(&config.globals, &config.qh)?;
+        let pool_size = bars.iter().map(|b| b.width * b.height * 4).sum::<u32>();
+        let pool = SlotPool::new(pool_size.max(1) as usize, &shm)?;
+        let shell = match kind {
+            SurfaceKind::Layer => Shell::Layer(LayerShell::bind(&config.globals, &config.qh)?),
+            SurfaceKind::Windowed => Shell::Xdg(XdgShell::bind(&config.globals, &config.qh)?),
+        };
         let compositor = CompositorState::bind(&config.globals, &config.qh)?;
 
-        Ok((
-            Self {
-                pool,
-                registry_state: RegistryState::new(&config.globals),
-                seat_state: SeatState::new(&config.globals, &config.qh),
-                output_state: OutputState::new(&config.globals, &config.qh),
-                config,
-                shm,
-                compositor,
-                layer_name: String::from(layer_name),
-                layer_shell,
-                window,
-                software_buffer: vec![start_pixel; (width * height) as usize],
-                exit: false,
-                pointer: None,
-                instances: Vec::new(),
-            },
-            event_queue,
-        ))
-    }
-    fn draw(&mut self) -> anyhow::Result<()> {
-        let width = self.config.width;
-        let height = self.config.height;
+        let mut bar = Self {
+            pool,
+            registry_state: RegistryState::new(&config.globals),
+            seat_state: SeatState::new(&config.globals, &config.qh),
+            output_state: OutputState::new(&config.globals, &config.qh),
+            config,
+            shm,
+            compositor,
+            shell,
+            windows: Vec::new(),
+            exit: false,
+            pointer: None,
+            instances: Vec::new(),
+        };
+        for definition in bars {
+            definition.builder().build(&mut bar);
+        }
+        bar.sync_outputs(&event_queue.handle(), None);
+        Ok((bar, event_queue))
+    }
+    /// Every window shown, starting with the bars in the order they were given to [`Bar::new`]
+    pub fn windows(&self) -> &[LayerWindow] {
+        &self.windows
+    }
+    /// Destroy every surface and release the pointer.  The requests still have to be flushed
+    /// to the compositor.
+    pub fn close(&mut self) {
+        self.instances.clear();
+        self.windows.clear();
+        if let Some(pointer) = self.pointer.take() {
+            pointer.release();
+        }
+    }
+    /// Whether a frame has been committed for every window on every output it should be on.
+    /// False while outputs are still being announced or changes to windows wait for a render.
+    pub fn all_drawn(&self) -> bool {
+        self.output_state
+            .outputs()
+            .all(|o| self.output_state.info(&o).is_some())
+            && self.windows.iter().all(|w| !w.0.borrow().changed)
+            && self.instances.iter().all(|i| i.drawn)
+    }
+    pub(crate) fn add_window(&mut self, mut state: WindowState) -> LayerWindow {
+        if let Shell::Xdg(_) = self.shell {
+            // a normal window is always shown, so the content is told it is revealed for good
+            if let Some(on_reveal) = state.auto_hide.take().and_then(|a| a.on_reveal) {
+                on_reveal(true);
+            }
+        }
+        state.changed = true;
+        let window = LayerWindow(Rc::new(RefCell::new(state)));
+        self.windows.push(window.clone());
+        window
+    }
+    fn state(&self, window: usize) -> Ref<'_, WindowState> {
+        self.windows[window].0.borrow()
+    }
+    /// Render the slint window into the buffer of every configured instance.
+    /// The window is resized to each instance in turn, so outputs of different sizes each get
+    /// their own layout.
+    pub fn render(&mut self) {
+        self.apply_changes();
+        self.update_auto_hide();
+        for (index, window) in self.windows.iter().enumerate() {
+            let state = window.0.borrow();
+            let mut redrawn = false;
+            for instance in self
+                .instances
+                .iter_mut()
+                .filter(|i| i.configured && i.window == index && i.visibility != Visibility::Hidden)
+            {
+                let size = PhysicalSize::new(instance.width, instance.height);
+                if state.window.size() != size {
+                    state.window.set_size(size);
+                }
+                if redrawn {
+                    state.window.request_redraw();
+                }
+                let rendered = state.window.draw_if_needed(|renderer| {
+                    renderer.render(&mut instance.software_buffer, instance.width as usize);
+                });
+                if rendered {
+                    instance.update_input_region(&state, &self.compositor);
+                }
+                redrawn |= rendered;
+            }
+        }
+    }
+    fn draw(&mut self, surface: &wl_surface::WlSurface) -> anyhow::Result<()> {
+        let Some(instance) = self
+            .instances
+            .iter_mut()
+            .find(|i| i.configured && i.surface.wl_surface() == surface)
+        else {
+            return Ok(());
+        };
+        let (width, height) = instance.surface_size(&self.windows[instance.window].0.borrow());
         let stride = width as i32 * 4;
 
-        let (buffer, canvas) = self
-            .pool
-            .create_buffer(
-                width as i32,
-                height as i32,
-                stride,
-                wl_shm::Format::Argb8888,
-            )
-            .expect("create buffer");
-        for (r, p) in canvas.iter_mut().zip(
-            self.software_buffer
-                .iter()
-                .flat_map(|p| [p.blue, p.green, p.red, p.alpha]),
-        ) {
-            *r = p;
+        let (buffer, canvas) = self.pool.create_buffer(
+            width as i32,
+            height as i32,
+            stride,
+            wl_shm::Format::Argb8888,
+        )?;
+        if instance.visibility == Visibility::Hidden {
+            // the trigger strip is invisible
+            canvas.fill(0);
+        } else {
+            for (r, p) in canvas.iter_mut().zip(
+                instance
+                    .software_buffer
+                    .iter()
+                    .flat_map(|p| [p.blue, p.green, p.red, p.alpha]),
+            ) {
+                *r = p;
+            }
         }
 
+        let surface = instance.surface.wl_surface();
         // Damage the entire window
-        for instance in self.instances.iter_mut().filter(|i| i.configured) {
-            instance
-                .layer
-                .wl_surface()
-                .damage_buffer(0, 0, width as i32, height as i32);
+        surface.damage_buffer(0, 0, width as i32, height as i32);
 
-            // Request our next frame
-            instance
-                .layer
-                .wl_surface()
-                .frame(&self.config.qh, instance.layer.wl_surface().clone());
-            // Attach and commit to present.
-            buffer
-                .attach_to(instance.layer.wl_surface())
-                .expect("buffer attach");
-            instance.layer.commit();
-        }
+        // Request our next frame
+        surface.frame(&self.config.qh, surface.clone());
+        // Attach and commit to present.
+        buffer.attach_to(surface)?;
+        surface.commit();
+        instance.drawn = true;
         Ok(())
     }
+    /// Apply what was changed through the [`LayerWindow`] handles since the last render
+    fn apply_changes(&mut self) {
+        let mut changed = false;
+        for index in 0..self.windows.len() {
+            let mut state = self.windows[index].0.borrow_mut();
+            if !std::mem::take(&mut state.changed) {
+                continue;
+            }
+            changed = true;
+            if !std::mem::take(&mut state.auto_hide_changed) {
+                continue;
+            }
+            if let Shell::Xdg(_) = self.shell {
+                // turned on later, but a normal window is still always shown
+                state.auto_hide = None;
+                continue;
+            }
+            let visibility = if state.auto_hide.as_ref().is_some_and(|a| a.enabled) {
+                Visibility::Leaving(Instant::now())
+            } else {
+                Visibility::Shown
+            };
+            drop(state);
+            for instance in 0..self.instances.len() {
+                if self.instances[instance].window == index {
+                    self.set_visibility(instance, visibility);
+                    // the exclusive zone depends on whether auto-hiding is enabled
+                    let instance = &self.instances[instance];
+                    instance.apply_size(&self.state(index));
+                    instance.surface.commit();
+                }
+            }
+        }
+        if changed {
+            let qh = self.config.qh.clone();
+            self.sync_outputs(&qh, None);
+        }
+    }
+    /// Advance the hide timers of every instance and let the slint content know when its window
+    /// starts to hide or reveal itself
+    fn update_auto_hide(&mut self) {
+        for index in 0..self.instances.len() {
+            let instance = &self.instances[index];
+            let state = self.state(instance.window);
+            let Some(auto_hide) = &state.auto_hide else {
+                continue;
+            };
+            if let Some(visibility) = instance.visibility.tick(auto_hide) {
+                drop(state);
+                self.set_visibility(index, visibility);
+            }
+        }
+        for (index, window) in self.windows.iter().enumerate() {
+            let mut state = window.0.borrow_mut();
+            if state.auto_hide.as_ref().is_none() {
+                continue;
+            }
+            let revealed = self
+                .instances
+                .iter()
+                .any(|i| i.window == index && i.visibility.revealed());
+            if state.revealed != revealed {
+                state.revealed = revealed;
+                if let Some(on_reveal) = state.auto_hide.as_ref().and_then(|a| a.on_reveal.as_ref())
+                {
+                    on_reveal(revealed);
+                }
+            }
+        }
+    }
+    /// Move an instance to a new point in the hide/reveal cycle, collapsing or expanding its
+    /// surface as needed
+    fn set_visibility(&mut self, index: usize, visibility: Visibility) {
+        let instance = &mut self.instances[index];
+        let was_hidden = instance.visibility == Visibility::Hidden;
+        instance.visibility = visibility;
+        if was_hidden != (visibility == Visibility::Hidden) {
+            event!(
+                Level::DEBUG,
+                "{} window on output {:?}",
+                if was_hidden { "revealing" } else { "hiding" },
+                instance.output.as_ref().map(Proxy::id)
+            );
+            let state = self.windows[instance.window].0.borrow();
+            instance.apply_size(&state);
+            instance.update_input_region(&state, &self.compositor);
+            instance.surface.commit();
+        }
+    }
+    /// The outputs that should have `window` according to its output filter, none if it is
+    /// hidden, minus the outputs it was hidden on.  `None` leaves the output up to the
+    /// compositor.  `removed` is skipped as it is still listed while being destroyed.
+    fn wanted_outputs(
+        &self,
+        window: usize,
+        removed: Option<&wl_output::WlOutput>,
+    ) -> Vec<Option<wl_output::WlOutput>> {
+        let state = self.state(window);
+        if !state.visible {
+            return Vec::new();
+        }
+        if state.focused_output {
+            return vec![None];
+        }
+        let filter = &state.outputs;
+        let mut wanted = self
+            .output_state
+            .outputs()
+            .filter(|o| Some(o) != removed)
+            .filter(|o| filter.matches(self.output_state.info(o).as_ref()));
+        let wanted = if filter.primary_only {
+            wanted.next().into_iter().collect()
+        } else {
+            wanted.collect::<Vec<_>>()
+        };
+        wanted
+            .into_iter()
+            .filter(|o| {
+                let info = self.output_state.info(o);
+                let name = info.as_ref().and_then(|i| i.name.as_ref());
+                !name.is_some_and(|name| state.hidden_on.contains(name))
+            })
+            .map(Some)
+            .collect()
+    }
+    /// Drop destroyed windows, then create, destroy and resize instances so that every wanted
+    /// output has exactly one instance of each window, of the right size
+    fn sync_outputs(&mut self, qh: &QueueHandle<Self>, removed: Option<&wl_output::WlOutput>) {
+        while let Some(index) = self.windows.iter().position(|w| w.0.borrow().destroyed) {
+            event!(
+                Level::DEBUG,
+                "destroying window '{}'",
+                self.state(index).namespace
+            );
+            self.windows.remove(index);
+            self.instances.retain(|i| i.window != index);
+            for instance in &mut self.instances {
+                if instance.window > index {
+                    instance.window -= 1;
+                }
+            }
+        }
+        if let Shell::Xdg(_) = self.shell {
+            // windows don't belong to an output
+            for index in 0..self.windows.len() {
+                let visible = self.state(index).visible;
+                let exists = self.instances.iter().any(|i| i.window == index);
+                if visible && !exists {
+                    self.create_window(qh, index);
+                } else if !visible && exists {
+                    self.instances.retain(|i| i.window != index);
+                }
+            }
+            return;
+        }
+        for window in 0..self.windows.len() {
+            let wanted = self.wanted_outputs(window, removed);
+            let before = self.instances.len();
+            self.instances
+                .retain(|i| i.window != window || wanted.contains(&i.output));
+            if self.instances.len() != before {
+                event!(
+                    Level::DEBUG,
+                    "removed {} instances of '{}'",
+                    before - self.instances.len(),
+                    self.state(window).namespace
+                );
+            }
+            for output in wanted {
+                let (width, height) = self.output_size(window, output.as_ref());
+                let state = self.windows[window].0.borrow();
+                if let Some(instance) = self
+                    .instances
+                    .iter_mut()
+                    .find(|i| i.window == window && i.output == output)
+                {
+                    if instance.resize(state.start_pixel, width, height) {
+                        event!(Level::DEBUG, "resizing window to {width}x{height}");
+                        // wait for the compositor to acknowledge the new size before drawing
+                        instance.configured = false;
+                        instance.apply_size(&state);
+                        instance.surface.commit();
+                    }
+                } else {
+                    drop(state);
+                    self.create_instance(qh, window, output, width, height);
+                }
+            }
+        }
+    }
+    fn create_instance(
+        &mut self,
+        qh: &QueueHandle<Self>,
+        window: usize,
+        output: Option<wl_output::WlOutput>,
+        width: u32,
+        height: u32,
+    ) {
+        let Shell::Layer(layer_shell) = &self.shell else {
+            return;
+        };
+        let state = self.windows[window].0.borrow();
+        let surface = self.compositor.create_surface(qh);
+        let layer = layer_shell.create_layer_surface(
+            qh,
+            surface,
+            state.layer,
+            Some(&state.namespace),
+            output.as_ref(),
+        );
+        layer.set_anchor(state.anchor);
+        let [top, right, bottom, left] = state.margin;
+        layer.set_margin(top, right, bottom, left);
+        layer.set_keyboard_interactivity(state.keyboard_interactivity);
+        let mut instance = BarInstance::new(
+            BarSurface::Layer(layer),
+            window,
+            output,
+            state.start_pixel,
+            width,
+            height,
+        );
+        if state.auto_hide.as_ref().is_some_and(|a| a.enabled) {
+            instance.visibility = Visibility::Hidden;
+        }
+        instance.apply_size(&state);
+        instance.update_input_region(&state, &self.compositor);
+        instance.surface.commit();
+        drop(state);
+        self.instances.push(instance);
+        event!(
+            Level::DEBUG,
+            "output created. {} layer surfaces exist",
+            self.instances.len()
+        );
+    }
+    /// Open `window` as a normal window in windowed mode.  It starts out at its configured size.
+    fn create_window(&mut self, qh: &QueueHandle<Self>, window: usize) {
+        let Shell::Xdg(xdg_shell) = &self.shell else {
+            return;
+        };
+        let state = self.windows[window].0.borrow();
+        let surface = self.compositor.create_surface(qh);
+        let toplevel = xdg_shell.create_window(surface, WindowDecorations::RequestServer, qh);
+        toplevel.set_title(state.namespace.clone());
+        toplevel.set_app_id(state.namespace.clone());
+        let instance = BarInstance::new(
+            BarSurface::Window(toplevel),
+            window,
+            None,
+            state.start_pixel,
+            state.width,
+            state.height,
+        );
+        instance.surface.commit();
+        event!(Level::DEBUG, "opened window for '{}'", state.namespace);
+        drop(state);
+        self.instances.push(instance);
+    }
+    /// Take the size picked by the compositor for a surface and draw it if this is the first
+    /// configure or the size changed.  Dimensions of 0 are left up to us.
+    fn configure_surface(&mut self, surface: &wl_surface::WlSurface, size: (u32, u32)) {
+        let Some(instance) = self
+            .instances
+            .iter_mut()
+            .find(|i| i.surface.wl_surface() == surface)
+        else {
+            event!(Level::WARN, "received configure for an unknown surface");
+            return;
+        };
+        let start_pixel = self.windows[instance.window].0.borrow().start_pixel;
+        // While hidden the configured size is the trigger strip, not the size of the window.
+        let (width, height) = match size {
+            _ if instance.visibility == Visibility::Hidden => (instance.width, instance.height),
+            (0, _) | (_, 0) => (instance.width, instance.height),
+            size => size,
+        };
+        let resized = instance.resize(start_pixel, width, height);
+        if !instance.configured || resized {
+            instance.configured = true;
+            self.render();
+            if let Err(e) = self.draw(surface) {
+                event!(Level::ERROR, "failed to draw configured surface: {e}");
+            }
+        }
+    }
+    /// The size `window` should have on `output`.  A window spanning its output gets the full
+    /// logical width (or height, for side anchors) of the output and keeps the configured
+    /// thickness.  Falls back to the configured size if the compositor hasn't sent any
+    /// information about the output yet.
+    fn output_size(&self, window: usize, output: Option<&wl_output::WlOutput>) -> (u32, u32) {
+        let state = self.state(window);
+        let (width, height) = (state.width, state.height);
+        let Some(output) = output.filter(|_| state.span_output) else {
+            return (width, height);
+        };
+        let Some((output_width, output_height)) = self
+            .output_state
+            .info(output)
+            .as_ref()
+            .and_then(logical_output_size)
+        else {
+            return (width, height);
+        };
+        if is_horizontal(state.anchor) {
+            (output_width, height)
+        } else {
+            (width, output_height)
+        }
+    }
+}
+
+/// Everything needed to show one bar.  Each bar renders its own slint window and gets one layer
+/// surface on every output its filter selects.
+pub struct BarDefinition {
+    pub window: Rc<MinimalSoftwareWindow>,
+    pub start_pixel: RgbaPixel,
+    pub position: Anchor,
+    pub layer_name: String,
+    pub outputs: OutputFilter,
+    /// fallback width, used until the size of an output is known
+    pub width: u32,
+    pub height: u32,
+    /// hide the bar until the pointer touches the screen edge
+    pub auto_hide: Option<AutoHide>,
+    /// the part of the bar that accepts pointer input
+    pub input_region: InputRegion,
+}
+
+impl BarDefinition {
+    /// A layer window on the top layer that spans its output and reserves its own thickness
+    pub fn builder(self) -> LayerWindowBuilder {
+        let builder = LayerWindowBuilder::new(self.window, self.width, self.height)
+            .namespace(self.layer_name)
+            .anchor(self.position)
+            .layer(Layer::Top)
+            .span_output(true)
+            .exclusive_zone(ExclusiveZone::Auto)
+            .outputs(self.outputs)
+            .start_pixel(self.start_pixel)
+            .input_region(self.input_region);
+        match self.auto_hide {
+            Some(auto_hide) => builder.auto_hide(auto_hide),
+            None => builder,
+        }
+    }
 }
 
 pub struct BarConfig {
     globals: GlobalList,
-    position: Anchor,
-    width: u32,
-    height: u32,
     qh: QueueHandle<Bar>,
 }
 impl BarConfig {
-    fn new(
-        conn: &Connection,
-        position: Anchor,
-        width: u32,
-        height: u32,
-    ) -> anyhow::Result<(Self, EventQueue)> {
+    fn new(conn: &Connection) -> anyhow::Result<(Self, EventQueue)> {
         let (globals, event_queue) = registry_queue_init(conn)?;
         let qh = event_queue.handle();
-        Ok((
-            Self {
-                qh,
-                globals,
-                position,
-                width,
-                height,
-            },
-            event_queue,
-        ))
+        Ok((Self { qh, globals }, event_queue))
     }
 }
 
+/// One layer surface (or normal window) showing a [`LayerWindow`]
 pub struct BarInstance {
     configured: bool,
-    layer: LayerSurface,
-    output: wl_output::WlOutput,
+    /// whether a buffer was ever committed
+    drawn: bool,
+    surface: BarSurface,
+    /// index of the [`LayerWindow`] this is an instance of
+    window: usize,
+    /// `None` for windows and layer surfaces put on an output by the compositor
+    output: Option<wl_output::WlOutput>,
+    width: u32,
+    height: u32,
+    software_buffer: Vec<RgbaPixel>,
+    visibility: Visibility,
+    /// the input region last sent to the compositor.  `None` is the whole surface.
+    input_rects: Option<Vec<Rect>>,
+}
+
+/// The role of the surface an instance draws to
+enum BarSurface {
+    Layer(LayerSurface),
+    Window(Window),
+}
+
+impl BarSurface {
+    fn wl_surface(&self) -> &wl_surface::WlSurface {
+        match self {
+            Self::Layer(layer) => layer.wl_surface(),
+            Self::Window(window) => window.wl_surface(),
+        }
+    }
+    fn commit(&self) {
+        self.wl_surface().commit();
+    }
 }
 
 impl BarInstance {
-    pub fn new(layer: LayerSurface, output: wl_output::WlOutput) -> Self {
+    fn new(
+        surface: BarSurface,
+        window: usize,
+        output: Option<wl_output::WlOutput>,
+        start_pixel: RgbaPixel,
+        width: u32,
+        height: u32,
+    ) -> Self {
         Self {
             configured: false,
-            layer,
+            drawn: false,
+            surface,
+            window,
             output,
+            width,
+            height,
+            software_buffer: vec![start_pixel; (width * height) as usize],
+            visibility: Visibility::Shown,
+            input_rects: None,
+        }
+    }
+    /// Tell the compositor which part of the surface accepts input, if that changed.  The whole
+    /// trigger strip of a hidden window accepts input.
+    fn update_input_region(&mut self, state: &WindowState, compositor: &CompositorState) {
+        let rects = if self.visibility == Visibility::Hidden {
+            None
+        } else {
+            state.input_region.rects(&self.software_buffer, self.width)
+        };
+        if rects == self.input_rects {
+            return;
+        }
+        let surface = self.surface.wl_surface();
+        match &rects {
+            None => surface.set_input_region(None),
+            Some(rects) => {
+                let region = match Region::new(compositor) {
+                    Ok(region) => region,
+                    Err(e) => {
+                        event!(Level::WARN, "unable to create input region: {e}");
+                        return;
+                    }
+                };
+                for rect in rects {
+                    region.add(rect.x, rect.y, rect.width, rect.height);
+                }
+                surface.set_input_region(Some(region.wl_region()));
+            }
+        }
+        event!(
+            Level::TRACE,
+            "input region set to {} rectangles",
+            rects.as_ref().map_or(1, Vec::len)
+        );
+        self.input_rects = rects;
+    }
+    /// The size of the layer surface.  This is the trigger strip while auto-hidden.
+    fn surface_size(&self, state: &WindowState) -> (u32, u32) {
+        match (&state.auto_hide, self.visibility) {
+            (Some(auto_hide), Visibility::Hidden) => {
+                if is_horizontal(state.anchor) {
+                    (self.width, auto_hide.trigger_size)
+                } else {
+                    (auto_hide.trigger_size, self.height)
+                }
+            }
+            _ => (self.width, self.height),
+        }
+    }
+    /// Request the current surface size and exclusive zone from the compositor.  Auto-hiding
+    /// windows never reserve space so other windows don't jump around when they reveal
+    /// themselves.  Normal windows are sized by the compositor.
+    fn apply_size(&self, state: &WindowState) {
+        let BarSurface::Layer(layer) = &self.surface else {
+            return;
+        };
+        let (width, height) = self.surface_size(state);
+        layer.set_size(width, height);
+        let exclusive_zone = match state.exclusive_zone {
+            _ if state.auto_hide.as_ref().is_some_and(|a| a.enabled) => 0,
+            ExclusiveZone::None => 0,
+            ExclusiveZone::Auto if is_horizontal(state.anchor) => height as i32,
+            ExclusiveZone::Auto => width as i32,
+            ExclusiveZone::Fixed(zone) => zone,
+        };
+        layer.set_exclusive_zone(exclusive_zone);
+    }
+    /// Change the size of the instance, reallocating its buffer.  Returns false if the size is
+    /// unchanged.
+    fn resize(&mut self, start_pixel: RgbaPixel, width: u32, height: u32) -> bool {
+        if (self.width, self.height) == (width, height) {
+            return false;
         }
+        self.width = width;
+        self.height = height;
+        self.software_buffer = vec![start_pixel; (width * height) as usize];
+        true
     }
 }
 
@@ -185,10 +742,12 @@ impl CompositorHandler for Bar {
         &mut self,
         _conn: &Connection,
         _qh: &QueueHandle<Self>,
-        _surface: &wl_surface::WlSurface,
+        surface: &wl_surface::WlSurface,
         _time: u32,
     ) {
-        self.draw().unwrap();
+        if let Err(e) = self.draw(surface) {
+            event!(Level::ERROR, "failed to draw frame: {e}");
+        }
     }
 }
 impl OutputHandler for Bar {
@@ -201,45 +760,34 @@ impl OutputHandler for Bar {
         qh: &QueueHandle<Self>,
         output: wl_output::WlOutput,
     ) {
-        let surface = self.compositor.create_surface(qh);
-        let layer = self.layer_shell.create_layer_surface(
-            qh,
-            surface,
-            Layer::Top,
-            Some(&self.layer_name),
-            Some(&output),
-        );
-        layer.set_anchor(self.config.position);
-        layer.set_size(self.config.width, self.config.height);
-        layer.set_exclusive_zone(self.config.height as i32);
-        layer.commit();
-        let instance = BarInstance::new(layer, output);
-        self.instances.push(instance);
-        event!(
-            Level::DEBUG,
-            "output created. {} outputs exist",
-            self.instances.len()
-        );
+        trace_output(&self.output_state, &output, "new output");
+        self.sync_outputs(qh, None);
     }
     fn update_output(
         &mut self,
         _conn: &Connection,
-        _qh: &QueueHandle<Self>,
-        _output: wl_output::WlOutput,
+        qh: &QueueHandle<Self>,
+        output: wl_output::WlOutput,
     ) {
+        trace_output(&self.output_state, &output, "output updated");
+        // the name and description may only arrive now, so the filter has to be checked again
+        self.sync_outputs(qh, None);
     }
     fn output_destroyed(
         &mut self,
         _conn: &Connection,
-        _qh: &QueueHandle<Self>,
+        qh: &QueueHandle<Self>,
         output: wl_output::WlOutput,
     ) {
-        self.instances.retain(|i| i.output != output);
+        self.instances
+            .retain(|i| i.output.as_ref() != Some(&output));
         event!(
             Level::DEBUG,
             "output destroyed. {} outputs remain",
             self.instances.len()
         );
+        // with `primary_only` another output may take over the bar
+        self.sync_outputs(qh, Some(&output));
     }
 }
 impl ShmHandler for Bar {
@@ -301,35 +849,53 @@ impl PointerHandler for Bar {
         use slint::platform::WindowEvent;
         use PointerEventKind::*;
         for event in events {
-            if self
+            let Some(index) = self
                 .instances
                 .iter()
-                .all(|i| event.surface != *i.layer.wl_surface())
-            {
+                .position(|i| event.surface == *i.surface.wl_surface())
+            else {
                 continue;
+            };
+            let auto_hide = self
+                .state(self.instances[index].window)
+                .auto_hide
+                .as_ref()
+                .is_some_and(|a| a.enabled);
+            match event.kind {
+                Enter { .. } if auto_hide => {
+                    self.set_visibility(index, Visibility::Shown);
+                }
+                Leave { .. } if auto_hide => {
+                    self.set_visibility(index, Visibility::Leaving(Instant::now()));
+                }
+                _ => {}
+            }
+            let instance = &self.instances[index];
+            let window = self.state(instance.window).window.clone();
+            // lay the window out for the output the pointer is on
+            let size = PhysicalSize::new(instance.width, instance.height);
+            if window.size() != size {
+                window.set_size(size);
             }
             let position = LogicalPosition::new(event.position.0 as f32, event.position.1 as f32);
             match event.kind {
                 Enter { .. } => {}
                 Leave { .. } => {}
                 Motion { .. } => {
-                    self.window
-                        .dispatch_event(WindowEvent::PointerMoved { position });
+                    window.dispatch_event(WindowEvent::PointerMoved { position });
                 }
                 Press {
                     button: button_id, ..
                 } => {
                     if let Some(button) = parse_button_id(button_id) {
-                        self.window
-                            .dispatch_event(WindowEvent::PointerPressed { position, button })
+                        window.dispatch_event(WindowEvent::PointerPressed { position, button })
                     }
                 }
                 Release {
                     button: button_id, ..
                 } => {
                     if let Some(button) = parse_button_id(button_id) {
-                        self.window
-                            .dispatch_event(WindowEvent::PointerReleased { position, button })
+                        window.dispatch_event(WindowEvent::PointerReleased { position, button })
                     }
                 }
                 Axis { .. } => {}
@@ -344,18 +910,29 @@ impl LayerShellHandler for Bar {
         _conn: &Connection,
         _qh: &QueueHandle<Self>,
         layer: &LayerSurface,
-        _configure: LayerSurfaceConfigure,
+        configure: LayerSurfaceConfigure,
         _serial: u32,
     ) {
-        let mut instance = self
-            .instances
-            .iter_mut()
-            .find(|i| i.layer == *layer)
-            .expect("unable to configure layer.  It doesn't exist");
-        if !instance.configured {
-            instance.configured = true;
-            self.draw().unwrap();
-        }
+        self.configure_surface(layer.wl_surface(), configure.new_size);
+    }
+}
+impl WindowHandler for Bar {
+    fn request_close(&mut self, _conn: &Connection, _qh: &QueueHandle<Self>, _window: &Window) {
+        self.exit = true;
+    }
+    fn configure(
+        &mut self,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+        window: &Window,
+        configure: WindowConfigure,
+        _serial: u32,
+    ) {
+        let (width, height) = configure.new_size;
+        self.configure_surface(
+            window.wl_surface(),
+            (width.map_or(0, |w| w.get()), height.map_or(0, |h| h.get())),
+        );
     }
 }
 
@@ -366,6 +943,8 @@ delegate_shm!(Bar);
 delegate_pointer!(Bar);
 delegate_registry!(Bar);
 delegate_layer!(Bar);
+delegate_xdg_shell!(Bar);
+delegate_xdg_window!(Bar);
 
 impl ProvidesRegistryState for Bar {
     fn registry(&mut self) -> &mut RegistryState {
@@ -374,6 +953,52 @@ impl ProvidesRegistryState for Bar {
     registry_handlers![OutputState, SeatState];
 }
 
+/// The size of an output in the compositor's logical coordinate space
+fn logical_output_size(info: &OutputInfo) -> Option<(u32, u32)> {
+    if let Some((width, height)) = info.logical_size {
+        return Some((width.try_into().ok()?, height.try_into().ok()?));
+    }
+    let mode = info.modes.iter().find(|m| m.current)?;
+    let (width, height) = mode.dimensions;
+    let scale = info.scale_factor.max(1);
+    let (width, height) = match info.transform {
+        Transform::_90 | Transform::_270 | Transform::Flipped90 | Transform::Flipped270 => {
+            (height, width)
+        }
+        _ => (width, height),
+    };
+    Some((
+        (width / scale).try_into().ok()?,
+        (height / scale).try_into().ok()?,
+    ))
+}
+
+/// Whether a bar with this anchor spans the width of the output
+fn is_horizontal(position: Anchor) -> bool {
+    position.contains(Anchor::TOP) || position.contains(Anchor::BOTTOM)
+}
+
+fn trace_output(output_state: &OutputState, output: &wl_output::WlOutput, message: &str) {
+    let Some(info) = output_state.info(output) else {
+        event!(Level::DEBUG, "{message}: no output info available yet");
+        return;
+    };
+    let mode = info
+        .modes
+        .iter()
+        .find(|m| m.current)
+        .map(|m| format!("{}x{}@{}", m.dimensions.0, m.dimensions.1, m.refresh_rate));
+    event!(
+        Level::DEBUG,
+        "{message}: name={:?} mode={:?} scale={} transform={:?} logical_size={:?}",
+        info.name,
+        mode,
+        info.scale_factor,
+        info.transform,
+        info.logical_size,
+    );
+}
+
 fn parse_button_id(id: u32) -> Option<PointerEventButton> {
     match id {
         272 => Some(PointerEventButton::Left),
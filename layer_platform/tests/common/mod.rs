@@ -0,0 +1,987 @@
+//! A tiny wayland compositor for driving [`layer_platform::Bar`] in tests.  It runs on its own
+//! thread, serves a single client over a socket pair and records everything the client commits,
+//! so tests can plug outputs in and out, send configures, move the pointer and look at the
+//! buffers that ended up on screen.  Besides layer-shell it offers a bare xdg-shell for windowed
+//! mode.
+use std::{
+    collections::HashMap,
+    fs::File,
+    os::unix::{fs::FileExt, net::UnixStream},
+    sync::{
+        mpsc::{self, RecvTimeoutError},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use wayland_protocols::xdg::shell::server::{xdg_surface, xdg_toplevel, xdg_wm_base};
+use wayland_protocols_wlr::layer_shell::v1::server::{zwlr_layer_shell_v1, zwlr_layer_surface_v1};
+use wayland_server::{
+    backend::{ClientData, GlobalId, ObjectId},
+    protocol::{
+        wl_buffer, wl_callback, wl_compositor, wl_output, wl_pointer, wl_region, wl_seat, wl_shm,
+        wl_shm_pool, wl_surface,
+    },
+    Client, DataInit, Dispatch, Display, DisplayHandle, GlobalDispatch, New, Resource, WEnum,
+};
+use zwlr_layer_surface_v1::Anchor;
+
+type Command = Box<dyn FnOnce(&mut State, &DisplayHandle) + Send>;
+
+/// Handle to the compositor thread.  The thread stops when this is dropped.
+pub struct Compositor {
+    commands: Option<mpsc::Sender<Command>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Compositor {
+    /// Start the compositor and connect a client to it
+    pub fn start() -> (Self, wayland_client::Connection) {
+        let (client, server) = UnixStream::pair().expect("unable to create a socket pair");
+        let (commands, receiver) = mpsc::channel::<Command>();
+        let thread = thread::spawn(move || serve(server, &receiver));
+        let conn = wayland_client::Connection::from_socket(client)
+            .expect("unable to connect to the mock compositor");
+        (
+            Self {
+                commands: Some(commands),
+                thread: Some(thread),
+            },
+            conn,
+        )
+    }
+    /// Run `f` on the compositor thread and wait for its result.  Events sent from `f` reach the
+    /// client before the reply to its next roundtrip.
+    fn with<R: Send + 'static>(
+        &self,
+        f: impl FnOnce(&mut State, &DisplayHandle) -> R + Send + 'static,
+    ) -> R {
+        let (reply, result) = mpsc::sync_channel(1);
+        self.commands
+            .as_ref()
+            .unwrap()
+            .send(Box::new(move |state, handle| {
+                let _ = reply.send(f(state, handle));
+            }))
+            .expect("the compositor thread died");
+        result.recv().expect("the compositor thread died")
+    }
+    /// Plug in an output with a single mode of `width`x`height` at scale 1
+    pub fn add_output(&self, name: &str, width: u32, height: u32) {
+        let name = name.to_owned();
+        self.with(move |state, handle| {
+            let global =
+                handle.create_global::<State, wl_output::WlOutput, String>(4, name.clone());
+            state.outputs.push(Output {
+                name,
+                width,
+                height,
+                global,
+                resources: Vec::new(),
+            });
+        });
+    }
+    /// Switch an output to a new mode
+    pub fn set_output_mode(&self, name: &str, width: u32, height: u32) {
+        let name = name.to_owned();
+        self.with(move |state, _| {
+            let output = state.output_mut(&name);
+            output.width = width;
+            output.height = height;
+            for resource in &output.resources {
+                send_output_info(resource, output);
+            }
+        });
+    }
+    /// Unplug an output.  Layer surfaces on it are closed.
+    pub fn remove_output(&self, name: &str) {
+        let name = name.to_owned();
+        self.with(move |state, handle| {
+            let index = state
+                .outputs
+                .iter()
+                .position(|o| o.name == name)
+                .unwrap_or_else(|| panic!("no output named {name}"));
+            let output = state.outputs.remove(index);
+            handle.remove_global::<State>(output.global);
+            for layer in &state.layers {
+                if layer.output.as_deref() == Some(&name) {
+                    layer.resource.closed();
+                }
+            }
+        });
+    }
+    /// Every live layer surface in the order they were created
+    pub fn layers(&self) -> Vec<Layer> {
+        self.with(|state, _| {
+            state
+                .layers
+                .iter()
+                .map(|layer| {
+                    let surface = &state.surfaces[&layer.surface.id()];
+                    Layer {
+                        namespace: layer.namespace.clone(),
+                        output: layer.output.clone(),
+                        anchor: layer.anchor,
+                        layer: layer.layer,
+                        size: layer.size,
+                        margin: layer.margin,
+                        exclusive_zone: layer.exclusive_zone,
+                        buffer: surface.buffer.clone(),
+                        damage: surface.damage.clone(),
+                        input_region: surface.input_region.clone(),
+                        commits: surface.commits,
+                    }
+                })
+                .collect()
+        })
+    }
+    /// Every live xdg toplevel in the order they were created
+    pub fn windows(&self) -> Vec<Toplevel> {
+        self.with(|state, _| {
+            state
+                .windows
+                .iter()
+                .map(|window| {
+                    let surface = &state.surfaces[&window.surface.id()];
+                    Toplevel {
+                        title: window.title.clone(),
+                        app_id: window.app_id.clone(),
+                        buffer: surface.buffer.clone(),
+                    }
+                })
+                .collect()
+        })
+    }
+    /// Resize the `window`th toplevel to `width`x`height`
+    pub fn configure_window(&self, window: usize, width: u32, height: u32) {
+        self.with(move |state, _| {
+            let serial = state.next_serial();
+            state.windows[window].configure(serial, width, height);
+        });
+    }
+    /// Send a configure of `width`x`height` to the `layer`th layer surface
+    pub fn configure(&self, layer: usize, width: u32, height: u32) {
+        self.with(move |state, _| {
+            let serial = state.next_serial();
+            state.layers[layer]
+                .resource
+                .configure(serial, width, height);
+        });
+    }
+    /// Stop answering layer surfaces that ask for a new size, like a busy compositor would, until
+    /// called again with `false`.  Configures can still be sent with [`Compositor::configure`].
+    pub fn hold_configures(&self, hold: bool) {
+        self.with(move |state, _| state.hold_configures = hold);
+    }
+    /// Fire every frame callback the client is waiting on
+    pub fn send_frames(&self) {
+        self.with(|state, _| {
+            for surface in state.surfaces.values_mut() {
+                for callback in surface.frames.drain(..) {
+                    callback.done(0);
+                }
+            }
+        });
+    }
+    /// Move the pointer onto the `layer`th layer surface
+    pub fn pointer_enter(&self, layer: usize, x: f64, y: f64) {
+        self.with(move |state, _| {
+            let surface = state.layers[layer].surface.clone();
+            let serial = state.next_serial();
+            state.pointer_event(|p| p.enter(serial, &surface, x, y));
+            state.pointer_focus = Some(surface);
+        });
+    }
+    pub fn pointer_motion(&self, x: f64, y: f64) {
+        self.with(move |state, _| state.pointer_event(|p| p.motion(0, x, y)));
+    }
+    /// Press or release a button, using the linux input event codes (272 is the left button)
+    pub fn pointer_button(&self, button: u32, pressed: bool) {
+        self.with(move |state, _| {
+            let serial = state.next_serial();
+            let button_state = if pressed {
+                wl_pointer::ButtonState::Pressed
+            } else {
+                wl_pointer::ButtonState::Released
+            };
+            state.pointer_event(|p| p.button(serial, 0, button, button_state));
+        });
+    }
+    pub fn pointer_leave(&self) {
+        self.with(|state, _| {
+            let Some(surface) = state.pointer_focus.take() else {
+                return;
+            };
+            let serial = state.next_serial();
+            state.pointer_event(|p| p.leave(serial, &surface));
+        });
+    }
+}
+
+impl Drop for Compositor {
+    fn drop(&mut self) {
+        drop(self.commands.take());
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// A buffer the client committed, copied out of its shm pool
+#[derive(Debug, Clone)]
+pub struct Frame {
+    pub width: u32,
+    pub height: u32,
+    /// Argb8888 pixels, which are BGRA in memory
+    pub data: Vec<u8>,
+}
+
+impl Frame {
+    /// The premultiplied RGBA value of a pixel
+    pub fn pixel(&self, x: u32, y: u32) -> [u8; 4] {
+        let index = ((y * self.width + x) * 4) as usize;
+        let [b, g, r, a]: [u8; 4] = self.data[index..index + 4].try_into().unwrap();
+        [r, g, b, a]
+    }
+}
+
+/// What the client has told the compositor about one of its windows
+#[derive(Debug, Clone)]
+pub struct Toplevel {
+    pub title: String,
+    pub app_id: String,
+    /// the last committed buffer
+    pub buffer: Option<Frame>,
+}
+
+/// What the client has told the compositor about one of its layer surfaces
+#[derive(Debug, Clone)]
+pub struct Layer {
+    pub namespace: String,
+    pub output: Option<String>,
+    pub anchor: Anchor,
+    /// the layer as its protocol value, 3 being overlay
+    pub layer: u32,
+    pub size: (u32, u32),
+    /// top, right, bottom, left
+    pub margin: [i32; 4],
+    pub exclusive_zone: i32,
+    /// the last committed buffer
+    pub buffer: Option<Frame>,
+    /// the damage sent with the last commit as `[x, y, width, height]`
+    pub damage: Vec<[i32; 4]>,
+    /// the committed input region.  `None` is the whole surface.
+    pub input_region: Option<Vec<[i32; 4]>>,
+    pub commits: usize,
+}
+
+fn serve(stream: UnixStream, commands: &mpsc::Receiver<Command>) {
+    let mut display = Display::<State>::new().expect("unable to create a display");
+    let mut handle = display.handle();
+    handle.create_global::<State, wl_compositor::WlCompositor, ()>(5, ());
+    handle.create_global::<State, wl_shm::WlShm, ()>(1, ());
+    handle.create_global::<State, zwlr_layer_shell_v1::ZwlrLayerShellV1, ()>(4, ());
+    handle.create_global::<State, wl_seat::WlSeat, ()>(5, ());
+    handle.create_global::<State, xdg_wm_base::XdgWmBase, ()>(2, ());
+    handle
+        .insert_client(stream, Arc::new(ClientState))
+        .expect("unable to add the client");
+    let mut state = State::default();
+    loop {
+        match commands.recv_timeout(Duration::from_millis(1)) {
+            Ok(command) => command(&mut state, &handle),
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => return,
+        }
+        // errors mean the client is gone, which tests notice on their side
+        let _ = display.dispatch_clients(&mut state);
+        let _ = display.flush_clients();
+    }
+}
+
+struct ClientState;
+impl ClientData for ClientState {}
+
+#[derive(Default)]
+struct State {
+    outputs: Vec<Output>,
+    surfaces: HashMap<ObjectId, Surface>,
+    layers: Vec<LayerSurface>,
+    windows: Vec<Window>,
+    pointers: Vec<wl_pointer::WlPointer>,
+    pointer_focus: Option<wl_surface::WlSurface>,
+    serial: u32,
+    /// don't configure layer surfaces that ask for a new size, see [`Compositor::hold_configures`]
+    hold_configures: bool,
+}
+
+impl State {
+    fn next_serial(&mut self) -> u32 {
+        self.serial += 1;
+        self.serial
+    }
+    fn output_mut(&mut self, name: &str) -> &mut Output {
+        self.outputs
+            .iter_mut()
+            .find(|o| o.name == name)
+            .unwrap_or_else(|| panic!("no output named {name}"))
+    }
+    /// Send an event to every pointer, followed by a frame
+    fn pointer_event(&self, event: impl Fn(&wl_pointer::WlPointer)) {
+        for pointer in &self.pointers {
+            event(pointer);
+            pointer.frame();
+        }
+    }
+    /// Apply the pending state of a surface.  Layer surfaces get configured on their first commit
+    /// and whenever they asked for a new size unless configures are held, with any dimension left
+    /// as 0 filled in from their output.  Windows are left to pick their own size on their first
+    /// commit.
+    fn commit(&mut self, surface: &wl_surface::WlSurface) {
+        let Some(data) = self.surfaces.get_mut(&surface.id()) else {
+            return;
+        };
+        if let Some(buffer) = data.pending_buffer.take() {
+            data.buffer = buffer.and_then(|buffer| {
+                let frame = read_buffer(&buffer);
+                buffer.release();
+                frame
+            });
+        }
+        data.damage = std::mem::take(&mut data.pending_damage);
+        data.frames.append(&mut data.pending_frames);
+        if let Some(region) = data.pending_input_region.take() {
+            data.input_region = region;
+        }
+        data.commits += 1;
+
+        if let Some(window) = self
+            .windows
+            .iter_mut()
+            .find(|w| w.surface == *surface && !w.configured)
+        {
+            self.serial += 1;
+            window.configure(self.serial, 0, 0);
+        }
+        if self.hold_configures {
+            return;
+        }
+        let Some(index) = self
+            .layers
+            .iter()
+            .position(|l| l.surface == *surface && l.configured_size != Some(l.size))
+        else {
+            return;
+        };
+        let layer = &self.layers[index];
+        let requested = layer.size;
+        let output = self
+            .outputs
+            .iter()
+            .find(|o| Some(&o.name) == layer.output.as_ref())
+            .or(self.outputs.first());
+        let (mut width, mut height) = layer.size;
+        if let Some(output) = output {
+            if width == 0 {
+                width = output.width;
+            }
+            if height == 0 {
+                height = output.height;
+            }
+        }
+        let serial = self.next_serial();
+        let layer = &mut self.layers[index];
+        layer.configured_size = Some(requested);
+        layer.resource.configure(serial, width, height);
+    }
+}
+
+struct Output {
+    name: String,
+    width: u32,
+    height: u32,
+    global: GlobalId,
+    resources: Vec<wl_output::WlOutput>,
+}
+
+#[derive(Default)]
+struct Surface {
+    /// `Some(None)` when a null buffer was attached
+    pending_buffer: Option<Option<wl_buffer::WlBuffer>>,
+    pending_damage: Vec<[i32; 4]>,
+    pending_frames: Vec<wl_callback::WlCallback>,
+    pending_input_region: Option<Option<Vec<[i32; 4]>>>,
+    buffer: Option<Frame>,
+    damage: Vec<[i32; 4]>,
+    input_region: Option<Vec<[i32; 4]>>,
+    /// frame callbacks waiting for [`Compositor::send_frames`]
+    frames: Vec<wl_callback::WlCallback>,
+    commits: usize,
+}
+
+/// Layer surface state is applied as soon as it is requested instead of on commit, which is
+/// close enough for the tests
+struct LayerSurface {
+    resource: zwlr_layer_surface_v1::ZwlrLayerSurfaceV1,
+    surface: wl_surface::WlSurface,
+    namespace: String,
+    output: Option<String>,
+    anchor: Anchor,
+    layer: u32,
+    size: (u32, u32),
+    margin: [i32; 4],
+    exclusive_zone: i32,
+    /// the size the client had asked for when it was last configured
+    configured_size: Option<(u32, u32)>,
+}
+
+struct Window {
+    surface: wl_surface::WlSurface,
+    xdg_surface: xdg_surface::XdgSurface,
+    toplevel: Option<xdg_toplevel::XdgToplevel>,
+    title: String,
+    app_id: String,
+    configured: bool,
+}
+
+impl Window {
+    fn configure(&mut self, serial: u32, width: u32, height: u32) {
+        let Some(toplevel) = &self.toplevel else {
+            return;
+        };
+        toplevel.configure(width as i32, height as i32, Vec::new());
+        self.xdg_surface.configure(serial);
+        self.configured = true;
+    }
+}
+
+struct BufferData {
+    pool: Arc<File>,
+    offset: i32,
+    width: i32,
+    height: i32,
+    stride: i32,
+}
+
+type RegionData = Mutex<Vec<[i32; 4]>>;
+
+/// Copy the pixels of a shm buffer
+fn read_buffer(buffer: &wl_buffer::WlBuffer) -> Option<Frame> {
+    let data = buffer.data::<BufferData>()?;
+    let mut bytes = vec![0; (data.stride * data.height) as usize];
+    data.pool
+        .read_exact_at(&mut bytes, data.offset as u64)
+        .ok()?;
+    let row = data.width as usize * 4;
+    Some(Frame {
+        width: data.width as u32,
+        height: data.height as u32,
+        data: bytes
+            .chunks(data.stride as usize)
+            .flat_map(|r| &r[..row])
+            .copied()
+            .collect(),
+    })
+}
+
+fn send_output_info(resource: &wl_output::WlOutput, output: &Output) {
+    resource.geometry(
+        0,
+        0,
+        0,
+        0,
+        wl_output::Subpixel::Unknown,
+        "mock".into(),
+        output.name.clone(),
+        wl_output::Transform::Normal,
+    );
+    resource.mode(
+        wl_output::Mode::Current | wl_output::Mode::Preferred,
+        output.width as i32,
+        output.height as i32,
+        60_000,
+    );
+    if resource.version() >= 2 {
+        resource.scale(1);
+    }
+    if resource.version() >= 4 {
+        resource.name(output.name.clone());
+        resource.description(format!("Mock output {}", output.name));
+    }
+    if resource.version() >= 2 {
+        resource.done();
+    }
+}
+
+impl GlobalDispatch<wl_compositor::WlCompositor, ()> for State {
+    fn bind(
+        _state: &mut Self,
+        _handle: &DisplayHandle,
+        _client: &Client,
+        resource: New<wl_compositor::WlCompositor>,
+        _global_data: &(),
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        data_init.init(resource, ());
+    }
+}
+
+impl Dispatch<wl_compositor::WlCompositor, ()> for State {
+    fn request(
+        state: &mut Self,
+        _client: &Client,
+        _resource: &wl_compositor::WlCompositor,
+        request: wl_compositor::Request,
+        _data: &(),
+        _dhandle: &DisplayHandle,
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        match request {
+            wl_compositor::Request::CreateSurface { id } => {
+                let surface = data_init.init(id, ());
+                state.surfaces.insert(surface.id(), Surface::default());
+            }
+            wl_compositor::Request::CreateRegion { id } => {
+                data_init.init(id, RegionData::default());
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Dispatch<wl_surface::WlSurface, ()> for State {
+    fn request(
+        state: &mut Self,
+        _client: &Client,
+        resource: &wl_surface::WlSurface,
+        request: wl_surface::Request,
+        _data: &(),
+        _dhandle: &DisplayHandle,
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        use wl_surface::Request;
+        if let Request::Commit = request {
+            state.commit(resource);
+            return;
+        }
+        let Some(surface) = state.surfaces.get_mut(&resource.id()) else {
+            return;
+        };
+        match request {
+            Request::Attach { buffer, .. } => surface.pending_buffer = Some(buffer),
+            Request::Damage {
+                x,
+                y,
+                width,
+                height,
+            }
+            | Request::DamageBuffer {
+                x,
+                y,
+                width,
+                height,
+            } => surface.pending_damage.push([x, y, width, height]),
+            Request::Frame { callback } => {
+                surface.pending_frames.push(data_init.init(callback, ()));
+            }
+            Request::SetInputRegion { region } => {
+                surface.pending_input_region = Some(
+                    region.and_then(|r| r.data::<RegionData>().map(|d| d.lock().unwrap().clone())),
+                );
+            }
+            _ => {}
+        }
+    }
+    fn destroyed(
+        state: &mut Self,
+        _client: wayland_server::backend::ClientId,
+        resource: &wl_surface::WlSurface,
+        _data: &(),
+    ) {
+        state.surfaces.remove(&resource.id());
+    }
+}
+
+impl Dispatch<wl_region::WlRegion, RegionData> for State {
+    fn request(
+        _state: &mut Self,
+        _client: &Client,
+        _resource: &wl_region::WlRegion,
+        request: wl_region::Request,
+        data: &RegionData,
+        _dhandle: &DisplayHandle,
+        _data_init: &mut DataInit<'_, Self>,
+    ) {
+        // subtracting isn't used by the bar
+        if let wl_region::Request::Add {
+            x,
+            y,
+            width,
+            height,
+        } = request
+        {
+            data.lock().unwrap().push([x, y, width, height]);
+        }
+    }
+}
+
+impl Dispatch<wl_callback::WlCallback, ()> for State {
+    fn request(
+        _state: &mut Self,
+        _client: &Client,
+        _resource: &wl_callback::WlCallback,
+        _request: wl_callback::Request,
+        _data: &(),
+        _dhandle: &DisplayHandle,
+        _data_init: &mut DataInit<'_, Self>,
+    ) {
+    }
+}
+
+impl GlobalDispatch<wl_shm::WlShm, ()> for State {
+    fn bind(
+        _state: &mut Self,
+        _handle: &DisplayHandle,
+        _client: &Client,
+        resource: New<wl_shm::WlShm>,
+        _global_data: &(),
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        let shm = data_init.init(resource, ());
+        shm.format(wl_shm::Format::Argb8888);
+        shm.format(wl_shm::Format::Xrgb8888);
+    }
+}
+
+impl Dispatch<wl_shm::WlShm, ()> for State {
+    fn request(
+        _state: &mut Self,
+        _client: &Client,
+        _resource: &wl_shm::WlShm,
+        request: wl_shm::Request,
+        _data: &(),
+        _dhandle: &DisplayHandle,
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        if let wl_shm::Request::CreatePool { id, fd, .. } = request {
+            data_init.init(id, Arc::new(File::from(fd)));
+        }
+    }
+}
+
+impl Dispatch<wl_shm_pool::WlShmPool, Arc<File>> for State {
+    fn request(
+        _state: &mut Self,
+        _client: &Client,
+        _resource: &wl_shm_pool::WlShmPool,
+        request: wl_shm_pool::Request,
+        data: &Arc<File>,
+        _dhandle: &DisplayHandle,
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        // buffers are read straight from the file, so resizing needs no handling
+        if let wl_shm_pool::Request::CreateBuffer {
+            id,
+            offset,
+            width,
+            height,
+            stride,
+            ..
+        } = request
+        {
+            data_init.init(
+                id,
+                BufferData {
+                    pool: data.clone(),
+                    offset,
+                    width,
+                    height,
+                    stride,
+                },
+            );
+        }
+    }
+}
+
+impl Dispatch<wl_buffer::WlBuffer, BufferData> for State {
+    fn request(
+        _state: &mut Self,
+        _client: &Client,
+        _resource: &wl_buffer::WlBuffer,
+        _request: wl_buffer::Request,
+        _data: &BufferData,
+        _dhandle: &DisplayHandle,
+        _data_init: &mut DataInit<'_, Self>,
+    ) {
+    }
+}
+
+impl GlobalDispatch<zwlr_layer_shell_v1::ZwlrLayerShellV1, ()> for State {
+    fn bind(
+        _state: &mut Self,
+        _handle: &DisplayHandle,
+        _client: &Client,
+        resource: New<zwlr_layer_shell_v1::ZwlrLayerShellV1>,
+        _global_data: &(),
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        data_init.init(resource, ());
+    }
+}
+
+impl Dispatch<zwlr_layer_shell_v1::ZwlrLayerShellV1, ()> for State {
+    fn request(
+        state: &mut Self,
+        _client: &Client,
+        _resource: &zwlr_layer_shell_v1::ZwlrLayerShellV1,
+        request: zwlr_layer_shell_v1::Request,
+        _data: &(),
+        _dhandle: &DisplayHandle,
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        if let zwlr_layer_shell_v1::Request::GetLayerSurface {
+            id,
+            surface,
+            output,
+            namespace,
+            layer,
+        } = request
+        {
+            let layer = match layer {
+                WEnum::Value(layer) => layer.into(),
+                WEnum::Unknown(layer) => layer,
+            };
+            state.layers.push(LayerSurface {
+                resource: data_init.init(id, ()),
+                surface,
+                namespace,
+                output: output.and_then(|o| o.data::<String>().cloned()),
+                anchor: Anchor::empty(),
+                layer,
+                size: (0, 0),
+                margin: [0; 4],
+                exclusive_zone: 0,
+                configured_size: None,
+            });
+        }
+    }
+}
+
+impl Dispatch<zwlr_layer_surface_v1::ZwlrLayerSurfaceV1, ()> for State {
+    fn request(
+        state: &mut Self,
+        _client: &Client,
+        resource: &zwlr_layer_surface_v1::ZwlrLayerSurfaceV1,
+        request: zwlr_layer_surface_v1::Request,
+        _data: &(),
+        _dhandle: &DisplayHandle,
+        _data_init: &mut DataInit<'_, Self>,
+    ) {
+        use zwlr_layer_surface_v1::Request;
+        let Some(layer) = state.layers.iter_mut().find(|l| l.resource == *resource) else {
+            return;
+        };
+        match request {
+            Request::SetSize { width, height } => layer.size = (width, height),
+            Request::SetAnchor {
+                anchor: WEnum::Value(anchor),
+            } => layer.anchor = anchor,
+            Request::SetExclusiveZone { zone } => layer.exclusive_zone = zone,
+            Request::SetMargin {
+                top,
+                right,
+                bottom,
+                left,
+            } => layer.margin = [top, right, bottom, left],
+            _ => {}
+        }
+    }
+    fn destroyed(
+        state: &mut Self,
+        _client: wayland_server::backend::ClientId,
+        resource: &zwlr_layer_surface_v1::ZwlrLayerSurfaceV1,
+        _data: &(),
+    ) {
+        state.layers.retain(|l| l.resource != *resource);
+    }
+}
+
+impl GlobalDispatch<wl_seat::WlSeat, ()> for State {
+    fn bind(
+        _state: &mut Self,
+        _handle: &DisplayHandle,
+        _client: &Client,
+        resource: New<wl_seat::WlSeat>,
+        _global_data: &(),
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        let seat = data_init.init(resource, ());
+        seat.capabilities(wl_seat::Capability::Pointer);
+        if seat.version() >= 2 {
+            seat.name("seat0".into());
+        }
+    }
+}
+
+impl Dispatch<wl_seat::WlSeat, ()> for State {
+    fn request(
+        state: &mut Self,
+        _client: &Client,
+        _resource: &wl_seat::WlSeat,
+        request: wl_seat::Request,
+        _data: &(),
+        _dhandle: &DisplayHandle,
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        if let wl_seat::Request::GetPointer { id } = request {
+            state.pointers.push(data_init.init(id, ()));
+        }
+    }
+}
+
+impl Dispatch<wl_pointer::WlPointer, ()> for State {
+    fn request(
+        _state: &mut Self,
+        _client: &Client,
+        _resource: &wl_pointer::WlPointer,
+        _request: wl_pointer::Request,
+        _data: &(),
+        _dhandle: &DisplayHandle,
+        _data_init: &mut DataInit<'_, Self>,
+    ) {
+    }
+    fn destroyed(
+        state: &mut Self,
+        _client: wayland_server::backend::ClientId,
+        resource: &wl_pointer::WlPointer,
+        _data: &(),
+    ) {
+        state.pointers.retain(|p| p != resource);
+    }
+}
+
+impl GlobalDispatch<wl_output::WlOutput, String> for State {
+    fn bind(
+        state: &mut Self,
+        _handle: &DisplayHandle,
+        _client: &Client,
+        resource: New<wl_output::WlOutput>,
+        name: &String,
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        let resource = data_init.init(resource, name.clone());
+        // the output may have been unplugged before the client got to bind it
+        if let Some(output) = state.outputs.iter_mut().find(|o| o.name == *name) {
+            send_output_info(&resource, output);
+            output.resources.push(resource);
+        }
+    }
+}
+
+impl Dispatch<wl_output::WlOutput, String> for State {
+    fn request(
+        _state: &mut Self,
+        _client: &Client,
+        _resource: &wl_output::WlOutput,
+        _request: wl_output::Request,
+        _data: &String,
+        _dhandle: &DisplayHandle,
+        _data_init: &mut DataInit<'_, Self>,
+    ) {
+    }
+}
+
+impl GlobalDispatch<xdg_wm_base::XdgWmBase, ()> for State {
+    fn bind(
+        _state: &mut Self,
+        _handle: &DisplayHandle,
+        _client: &Client,
+        resource: New<xdg_wm_base::XdgWmBase>,
+        _global_data: &(),
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        data_init.init(resource, ());
+    }
+}
+
+impl Dispatch<xdg_wm_base::XdgWmBase, ()> for State {
+    fn request(
+        state: &mut Self,
+        _client: &Client,
+        _resource: &xdg_wm_base::XdgWmBase,
+        request: xdg_wm_base::Request,
+        _data: &(),
+        _dhandle: &DisplayHandle,
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        // popups and their positioners aren't used by the bar
+        if let xdg_wm_base::Request::GetXdgSurface { id, surface } = request {
+            state.windows.push(Window {
+                surface,
+                xdg_surface: data_init.init(id, ()),
+                toplevel: None,
+                title: String::new(),
+                app_id: String::new(),
+                configured: false,
+            });
+        }
+    }
+}
+
+impl Dispatch<xdg_surface::XdgSurface, ()> for State {
+    fn request(
+        state: &mut Self,
+        _client: &Client,
+        resource: &xdg_surface::XdgSurface,
+        request: xdg_surface::Request,
+        _data: &(),
+        _dhandle: &DisplayHandle,
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        if let xdg_surface::Request::GetToplevel { id } = request {
+            let toplevel = data_init.init(id, ());
+            if let Some(window) = state
+                .windows
+                .iter_mut()
+                .find(|w| w.xdg_surface == *resource)
+            {
+                window.toplevel = Some(toplevel);
+            }
+        }
+    }
+    fn destroyed(
+        state: &mut Self,
+        _client: wayland_server::backend::ClientId,
+        resource: &xdg_surface::XdgSurface,
+        _data: &(),
+    ) {
+        state.windows.retain(|w| w.xdg_surface != *resource);
+    }
+}
+
+impl Dispatch<xdg_toplevel::XdgToplevel, ()> for State {
+    fn request(
+        state: &mut Self,
+        _client: &Client,
+        resource: &xdg_toplevel::XdgToplevel,
+        request: xdg_toplevel::Request,
+        _data: &(),
+        _dhandle: &DisplayHandle,
+        _data_init: &mut DataInit<'_, Self>,
+    ) {
+        let Some(window) = state
+            .windows
+            .iter_mut()
+            .find(|w| w.toplevel.as_ref() == Some(resource))
+        else {
+            return;
+        };
+        match request {
+            xdg_toplevel::Request::SetTitle { title } => window.title = title,
+            xdg_toplevel::Request::SetAppId { app_id } => window.app_id = app_id,
+            _ => {}
+        }
+    }
+}
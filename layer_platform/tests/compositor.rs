@@ -0,0 +1,376 @@
+//! Runs [`Bar`] against the mock compositor in `common` to check how it reacts to outputs
+//! coming and going, configures, frame callbacks and pointer input.
+mod common;
+
+use common::Compositor;
+use layer_platform::{
+    Anchor, AutoHide, Bar, BarDefinition, EventQueue, ExclusiveZone, InputRegion, Layer,
+    LayerShellPlatform, LayerWindowBuilder, OutputFilter, RgbaPixel, SurfaceKind,
+};
+use slint::platform::software_renderer::{MinimalSoftwareWindow, RepaintBufferType};
+use slint_interpreter::{ComponentHandle, ComponentInstance, Value};
+
+const RED: [u8; 4] = [255, 0, 0, 255];
+
+/// A red bar that counts clicks
+const SOURCE: &str = r#"
+export component Bar inherits Window {
+    in-out property <int> clicks;
+    Rectangle {
+        background: red;
+    }
+    TouchArea {
+        clicked => { clicks += 1; }
+    }
+}
+"#;
+
+/// Create one bar per `(layer_name, anchor)`, all showing [`SOURCE`]
+fn bars(
+    specs: &[(&str, Anchor)],
+    outputs: &OutputFilter,
+) -> (Vec<BarDefinition>, Vec<ComponentInstance>) {
+    let windows = specs
+        .iter()
+        .map(|_| MinimalSoftwareWindow::new(RepaintBufferType::NewBuffer))
+        .collect::<Vec<_>>();
+    slint::platform::set_platform(Box::new(LayerShellPlatform::new(windows.clone()))).unwrap();
+    let mut compiler = slint_interpreter::ComponentCompiler::new();
+    let definition =
+        spin_on::spin_on(compiler.build_from_source(SOURCE.into(), "bar.slint".into()))
+            .unwrap_or_else(|| panic!("failed to compile: {:?}", compiler.diagnostics()));
+    let uis = specs
+        .iter()
+        .map(|_| {
+            let ui = definition.create().unwrap();
+            ui.show().unwrap();
+            ui
+        })
+        .collect();
+    let definitions = specs
+        .iter()
+        .zip(windows)
+        .map(|(&(layer_name, position), window)| BarDefinition {
+            window,
+            start_pixel: RgbaPixel::transparent(),
+            position,
+            layer_name: layer_name.to_owned(),
+            outputs: outputs.clone(),
+            width: 1000,
+            height: 30,
+            auto_hide: None,
+            input_region: InputRegion::Full,
+        })
+        .collect();
+    (definitions, uis)
+}
+
+/// Let the bar and the compositor finish talking to each other
+fn settle(bar: &mut Bar, event_queue: &mut EventQueue) {
+    for _ in 0..4 {
+        event_queue.roundtrip(bar).unwrap();
+        bar.render();
+    }
+}
+
+fn clicks(ui: &ComponentInstance) -> Value {
+    ui.get_property("clicks").unwrap()
+}
+
+#[test]
+fn hot_plug() {
+    let (compositor, conn) = Compositor::start();
+    compositor.add_output("DP-1", 1920, 1080);
+    let (definitions, _uis) = bars(&[("bar", Anchor::TOP)], &OutputFilter::default());
+    let (mut bar, mut event_queue) =
+        Bar::with_connection(&conn, definitions, SurfaceKind::Layer).unwrap();
+    assert!(!bar.all_drawn());
+    settle(&mut bar, &mut event_queue);
+    assert!(bar.all_drawn());
+
+    let layers = compositor.layers();
+    assert_eq!(layers.len(), 1);
+    assert_eq!(layers[0].namespace, "bar");
+    assert_eq!(layers[0].output.as_deref(), Some("DP-1"));
+    assert_eq!(layers[0].anchor.bits(), Anchor::TOP.bits());
+    assert_eq!(layers[0].size, (1920, 30));
+    assert_eq!(layers[0].exclusive_zone, 30);
+    let buffer = layers[0].buffer.as_ref().expect("nothing was committed");
+    assert_eq!((buffer.width, buffer.height), (1920, 30));
+
+    compositor.add_output("HDMI-A-1", 1280, 1024);
+    settle(&mut bar, &mut event_queue);
+    let layers = compositor.layers();
+    assert_eq!(layers.len(), 2);
+    assert_eq!(layers[1].output.as_deref(), Some("HDMI-A-1"));
+    assert_eq!(layers[1].size, (1280, 30));
+
+    compositor.set_output_mode("DP-1", 2560, 1440);
+    settle(&mut bar, &mut event_queue);
+    let layers = compositor.layers();
+    assert_eq!(layers[0].size, (2560, 30));
+    let buffer = layers[0].buffer.as_ref().unwrap();
+    assert_eq!((buffer.width, buffer.height), (2560, 30));
+
+    compositor.remove_output("DP-1");
+    settle(&mut bar, &mut event_queue);
+    let layers = compositor.layers();
+    assert_eq!(layers.len(), 1);
+    assert_eq!(layers[0].output.as_deref(), Some("HDMI-A-1"));
+}
+
+#[test]
+fn output_filter() {
+    let (compositor, conn) = Compositor::start();
+    compositor.add_output("DP-1", 1920, 1080);
+    compositor.add_output("HDMI-A-1", 1280, 1024);
+    let filter = OutputFilter {
+        include: vec!["HDMI-*".into()],
+        ..OutputFilter::default()
+    };
+    let (definitions, _uis) = bars(&[("bar", Anchor::TOP)], &filter);
+    let (mut bar, mut event_queue) =
+        Bar::with_connection(&conn, definitions, SurfaceKind::Layer).unwrap();
+    settle(&mut bar, &mut event_queue);
+
+    let layers = compositor.layers();
+    assert_eq!(layers.len(), 1);
+    assert_eq!(layers[0].output.as_deref(), Some("HDMI-A-1"));
+}
+
+#[test]
+fn damage() {
+    let (compositor, conn) = Compositor::start();
+    compositor.add_output("DP-1", 800, 600);
+    let (definitions, _uis) = bars(&[("bar", Anchor::BOTTOM)], &OutputFilter::default());
+    let (mut bar, mut event_queue) =
+        Bar::with_connection(&conn, definitions, SurfaceKind::Layer).unwrap();
+    settle(&mut bar, &mut event_queue);
+
+    let layer = &compositor.layers()[0];
+    let buffer = layer.buffer.as_ref().expect("nothing was committed");
+    assert_eq!(buffer.pixel(0, 0), RED);
+    assert_eq!(buffer.pixel(799, 29), RED);
+    assert_eq!(layer.damage, vec![[0, 0, 800, 30]]);
+
+    // every frame callback is answered with a fully damaged buffer
+    let commits = layer.commits;
+    compositor.send_frames();
+    settle(&mut bar, &mut event_queue);
+    let layer = &compositor.layers()[0];
+    assert!(layer.commits > commits);
+    assert_eq!(layer.damage, vec![[0, 0, 800, 30]]);
+    assert_eq!(layer.buffer.as_ref().unwrap().pixel(400, 15), RED);
+}
+
+#[test]
+fn configure_resizes() {
+    let (compositor, conn) = Compositor::start();
+    compositor.add_output("DP-1", 800, 600);
+    let (definitions, _uis) = bars(&[("bar", Anchor::TOP)], &OutputFilter::default());
+    let (mut bar, mut event_queue) =
+        Bar::with_connection(&conn, definitions, SurfaceKind::Layer).unwrap();
+    settle(&mut bar, &mut event_queue);
+
+    compositor.configure(0, 400, 20);
+    settle(&mut bar, &mut event_queue);
+    let layer = &compositor.layers()[0];
+    let buffer = layer.buffer.as_ref().unwrap();
+    assert_eq!((buffer.width, buffer.height), (400, 20));
+    assert_eq!(layer.damage, vec![[0, 0, 400, 20]]);
+    assert_eq!(buffer.pixel(399, 19), RED);
+}
+
+#[test]
+fn mode_change_waits_for_configure() {
+    let (compositor, conn) = Compositor::start();
+    compositor.add_output("DP-1", 800, 600);
+    let (definitions, _uis) = bars(&[("bar", Anchor::TOP)], &OutputFilter::default());
+    let (mut bar, mut event_queue) =
+        Bar::with_connection(&conn, definitions, SurfaceKind::Layer).unwrap();
+    settle(&mut bar, &mut event_queue);
+
+    // the new size is requested right away, but nothing is drawn at it before it is acknowledged
+    compositor.hold_configures(true);
+    compositor.set_output_mode("DP-1", 1024, 768);
+    settle(&mut bar, &mut event_queue);
+    compositor.send_frames();
+    settle(&mut bar, &mut event_queue);
+    let layer = &compositor.layers()[0];
+    assert_eq!(layer.size, (1024, 30));
+    let buffer = layer.buffer.as_ref().unwrap();
+    assert_eq!((buffer.width, buffer.height), (800, 30));
+
+    compositor.configure(0, 1024, 30);
+    settle(&mut bar, &mut event_queue);
+    let buffer = compositor.layers()[0].buffer.clone().unwrap();
+    assert_eq!((buffer.width, buffer.height), (1024, 30));
+    assert_eq!(buffer.pixel(1023, 29), RED);
+}
+
+#[test]
+fn windowed() {
+    let (compositor, conn) = Compositor::start();
+    compositor.add_output("DP-1", 800, 600);
+    let (definitions, _uis) = bars(&[("bar", Anchor::TOP)], &OutputFilter::default());
+    let (mut bar, mut event_queue) =
+        Bar::with_connection(&conn, definitions, SurfaceKind::Windowed).unwrap();
+    settle(&mut bar, &mut event_queue);
+
+    assert!(compositor.layers().is_empty());
+    let windows = compositor.windows();
+    assert_eq!(windows.len(), 1);
+    assert_eq!(windows[0].title, "bar");
+    assert_eq!(windows[0].app_id, "bar");
+    // the compositor left the size up to the bar, so it uses its configured size
+    let buffer = windows[0].buffer.as_ref().expect("nothing was committed");
+    assert_eq!((buffer.width, buffer.height), (1000, 30));
+    assert_eq!(buffer.pixel(0, 0), RED);
+
+    compositor.configure_window(0, 640, 48);
+    settle(&mut bar, &mut event_queue);
+    let buffer = compositor.windows()[0].buffer.clone().unwrap();
+    assert_eq!((buffer.width, buffer.height), (640, 48));
+    assert_eq!(buffer.pixel(639, 47), RED);
+}
+
+#[test]
+fn auto_hide_toggle() {
+    let (compositor, conn) = Compositor::start();
+    compositor.add_output("DP-1", 800, 600);
+    let (mut definitions, _uis) = bars(&[("bar", Anchor::TOP)], &OutputFilter::default());
+    definitions[0].auto_hide = Some(AutoHide::default());
+    let (mut bar, mut event_queue) =
+        Bar::with_connection(&conn, definitions, SurfaceKind::Layer).unwrap();
+    settle(&mut bar, &mut event_queue);
+    let layer = &compositor.layers()[0];
+    assert_eq!(layer.size, (800, 2));
+    assert_eq!(layer.exclusive_zone, 0);
+
+    // revealed, but still not reserving space
+    compositor.pointer_enter(0, 10.0, 1.0);
+    settle(&mut bar, &mut event_queue);
+    let layer = &compositor.layers()[0];
+    assert_eq!(layer.size, (800, 30));
+    assert_eq!(layer.exclusive_zone, 0);
+
+    // turning auto-hide off while shown has to reserve space without a visibility change
+    bar.windows()[0].set_auto_hide(false);
+    settle(&mut bar, &mut event_queue);
+    let layer = &compositor.layers()[0];
+    assert_eq!(layer.size, (800, 30));
+    assert_eq!(layer.exclusive_zone, 30);
+
+    bar.windows()[0].set_auto_hide(true);
+    settle(&mut bar, &mut event_queue);
+    assert_eq!(compositor.layers()[0].exclusive_zone, 0);
+}
+
+#[test]
+fn auto_hide_enabled_later() {
+    let (compositor, conn) = Compositor::start();
+    compositor.add_output("DP-1", 800, 600);
+    let (definitions, _uis) = bars(&[("bar", Anchor::TOP)], &OutputFilter::default());
+    let (mut bar, mut event_queue) =
+        Bar::with_connection(&conn, definitions, SurfaceKind::Layer).unwrap();
+    settle(&mut bar, &mut event_queue);
+    assert_eq!(compositor.layers()[0].exclusive_zone, 30);
+
+    // built without auto-hide, so turning it off changes nothing
+    bar.windows()[0].set_auto_hide(false);
+    settle(&mut bar, &mut event_queue);
+    assert_eq!(compositor.layers()[0].exclusive_zone, 30);
+
+    bar.windows()[0].set_auto_hide(true);
+    settle(&mut bar, &mut event_queue);
+    assert_eq!(compositor.layers()[0].exclusive_zone, 0);
+
+    bar.windows()[0].set_auto_hide(false);
+    settle(&mut bar, &mut event_queue);
+    let layer = &compositor.layers()[0];
+    assert_eq!(layer.size, (800, 30));
+    assert_eq!(layer.exclusive_zone, 30);
+}
+
+#[test]
+fn input_routing() {
+    let (compositor, conn) = Compositor::start();
+    compositor.add_output("DP-1", 800, 600);
+    let (definitions, uis) = bars(
+        &[("top", Anchor::TOP), ("bottom", Anchor::BOTTOM)],
+        &OutputFilter::default(),
+    );
+    let (mut bar, mut event_queue) =
+        Bar::with_connection(&conn, definitions, SurfaceKind::Layer).unwrap();
+    settle(&mut bar, &mut event_queue);
+
+    let layers = compositor.layers();
+    let bottom = layers.iter().position(|l| l.namespace == "bottom").unwrap();
+    compositor.pointer_enter(bottom, 10.0, 10.0);
+    compositor.pointer_motion(20.0, 15.0);
+    compositor.pointer_button(272, true);
+    compositor.pointer_button(272, false);
+    compositor.pointer_leave();
+    settle(&mut bar, &mut event_queue);
+
+    assert_eq!(clicks(&uis[0]), Value::Number(0.0));
+    assert_eq!(clicks(&uis[1]), Value::Number(1.0));
+    // without auto-hide or an input region the whole surface takes input
+    assert!(compositor.layers().iter().all(|l| l.input_region.is_none()));
+}
+
+#[test]
+fn layer_window() {
+    let (compositor, conn) = Compositor::start();
+    compositor.add_output("DP-1", 800, 600);
+    let (mut definitions, _uis) = bars(
+        &[("bar", Anchor::TOP), ("osd", Anchor::empty())],
+        &OutputFilter::default(),
+    );
+    let osd = definitions.pop().unwrap().window;
+    let (mut bar, mut event_queue) =
+        Bar::with_connection(&conn, definitions, SurfaceKind::Layer).unwrap();
+    let osd = LayerWindowBuilder::new(osd, 200, 100)
+        .namespace("osd")
+        .layer(Layer::Overlay)
+        .focused_output(true)
+        .margin(0, 0, 50, 0)
+        .exclusive_zone(ExclusiveZone::Fixed(-1))
+        .build(&mut bar);
+    settle(&mut bar, &mut event_queue);
+
+    let layers = compositor.layers();
+    assert_eq!(layers.len(), 2);
+    let layer = &layers[1];
+    assert_eq!(layer.namespace, "osd");
+    // left up to the compositor
+    assert_eq!(layer.output, None);
+    assert_eq!(layer.layer, 3);
+    assert!(layer.anchor.is_empty());
+    assert_eq!(layer.size, (200, 100));
+    assert_eq!(layer.margin, [0, 0, 50, 0]);
+    assert_eq!(layer.exclusive_zone, -1);
+    assert_eq!(layer.buffer.as_ref().unwrap().pixel(199, 99), RED);
+
+    // only bars follow outputs
+    compositor.add_output("HDMI-A-1", 1280, 1024);
+    settle(&mut bar, &mut event_queue);
+    assert_eq!(compositor.layers().len(), 3);
+
+    osd.hide();
+    settle(&mut bar, &mut event_queue);
+    assert_eq!(compositor.layers().len(), 2);
+
+    osd.set_size(300, 60);
+    osd.show();
+    settle(&mut bar, &mut event_queue);
+    let layers = compositor.layers();
+    assert_eq!(layers.len(), 3);
+    let buffer = layers[2].buffer.as_ref().unwrap();
+    assert_eq!((buffer.width, buffer.height), (300, 60));
+
+    osd.destroy();
+    settle(&mut bar, &mut event_queue);
+    assert_eq!(compositor.layers().len(), 2);
+    assert_eq!(bar.windows().len(), 1);
+}
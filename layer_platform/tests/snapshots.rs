@@ -0,0 +1,60 @@
+//! Renders every case headlessly and compares it against the png checked in next to its `.slint`
+//! file in `tests/snapshots`.  Run with `UPDATE_SNAPSHOTS=1` to write new pngs after an
+//! intended change.
+use std::path::Path;
+
+use layer_platform::{read_png, Headless, LayerShellPlatform, RgbaPixel};
+use slint::{
+    platform::software_renderer::{MinimalSoftwareWindow, RepaintBufferType},
+    ComponentHandle,
+};
+
+/// name of the slint file and the size to render it at
+const CASES: &[(&str, u32, u32)] = &[("blocks", 200, 40), ("gradient", 100, 20)];
+
+#[test]
+fn snapshots() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/snapshots");
+    let update = std::env::var_os("UPDATE_SNAPSHOTS").is_some();
+    let windows = CASES
+        .iter()
+        .map(|_| MinimalSoftwareWindow::new(RepaintBufferType::NewBuffer))
+        .collect::<Vec<_>>();
+    slint::platform::set_platform(Box::new(LayerShellPlatform::new(windows.clone()))).unwrap();
+
+    let mut failures = Vec::new();
+    for (&(name, width, height), window) in CASES.iter().zip(windows) {
+        let mut compiler = slint_interpreter::ComponentCompiler::new();
+        let definition =
+            spin_on::spin_on(compiler.build_from_path(dir.join(format!("{name}.slint"))))
+                .unwrap_or_else(|| {
+                    panic!("{name} failed to compile: {:?}", compiler.diagnostics())
+                });
+        let ui = definition.create().unwrap();
+        ui.show().unwrap();
+
+        let mut headless = Headless::new(window, RgbaPixel::default(), width, height);
+        headless.render();
+        let mut actual = Vec::new();
+        headless.write_png(&mut actual).unwrap();
+
+        let expected_path = dir.join(format!("{name}.png"));
+        if update {
+            std::fs::write(&expected_path, &actual).unwrap();
+            continue;
+        }
+        let Ok(expected) = std::fs::File::open(&expected_path) else {
+            failures.push(format!("{name}: no snapshot, run with UPDATE_SNAPSHOTS=1"));
+            continue;
+        };
+        if read_png(expected).unwrap() != read_png(actual.as_slice()).unwrap() {
+            let actual_path = Path::new(env!("CARGO_TARGET_TMPDIR")).join(format!("{name}.png"));
+            std::fs::write(&actual_path, &actual).unwrap();
+            failures.push(format!(
+                "{name}: differs from snapshot, got '{}'",
+                actual_path.to_string_lossy()
+            ));
+        }
+    }
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}